@@ -0,0 +1,144 @@
+use std::cell::Cell;
+
+use crate::core::settings;
+
+// An interval written as center + c1*e1 + c2*e2 + ..., where every noise symbol e is somewhere in [-1..1].
+// Values that share a symbol move together, so x * (1 - x) doesn't count the uncertainty of x twice
+#[derive(Debug, Clone, PartialEq)]
+pub struct Affine {
+    center: f64,
+    terms: Vec<(usize, f64)>, // (noise symbol, coefficient), sorted by symbol
+    range: (f64, f64),        // never wider than what plain interval arithmetic gives
+}
+
+thread_local! {
+    static NEXT_SYMBOL: Cell<usize> = const { Cell::new(0) };
+}
+
+fn fresh_symbol() -> usize {
+    NEXT_SYMBOL.with(|next| {
+        let symbol = next.get();
+        next.set(symbol + 1);
+        symbol
+    })
+}
+
+impl Affine {
+    // A new independent uncertainty, like an interval literal
+    pub fn new(min: f64, max: f64) -> Self {
+        let radius = (max - min) / 2.0;
+        let terms = if radius > 0.0 && radius.is_finite() { vec![(fresh_symbol(), radius)] } else { Vec::new() };
+        Self { center: min + radius, terms, range: (min, max) }
+    }
+
+    pub fn range(&self) -> (f64, f64) {
+        self.range
+    }
+
+    // Only keeps the part of the range that plain interval arithmetic also allows
+    pub fn clamp(mut self, (min, max): (f64, f64)) -> Self {
+        self.range = (self.range.0.max(min), self.range.1.min(max));
+        self
+    }
+
+    fn radius(&self) -> f64 {
+        self.terms.iter().map(|(_, c)| c.abs()).sum()
+    }
+
+    fn from_form(center: f64, mut terms: Vec<(usize, f64)>) -> Self {
+        terms.retain(|(_, c)| *c != 0.0);
+        let mut form = Self { center, terms, range: (center, center) };
+        // Every step rounds to nearest, the error gets a symbol of its own so the range still holds the exact result
+        if settings::current().outward_rounding {
+            let error = f64::EPSILON * (center.abs() + form.radius());
+            if error > 0.0 {
+                form.terms.push((fresh_symbol(), error));
+            }
+        }
+        let radius = form.radius();
+        form.range = (center - radius, center + radius);
+        form
+    }
+
+    // a * self + b * other, symbol by symbol
+    fn combine(&self, other: &Affine, a: f64, b: f64) -> Vec<(usize, f64)> {
+        let (mut i, mut j) = (0, 0);
+        let mut terms = Vec::new();
+        while i < self.terms.len() || j < other.terms.len() {
+            match (self.terms.get(i), other.terms.get(j)) {
+                (Some(&(s, c)), Some(&(t, d))) if s == t => {
+                    terms.push((s, a * c + b * d));
+                    i += 1;
+                    j += 1;
+                }
+                (Some(&(s, c)), Some(&(t, _))) if s < t => {
+                    terms.push((s, a * c));
+                    i += 1;
+                }
+                (Some(&(s, c)), None) => {
+                    terms.push((s, a * c));
+                    i += 1;
+                }
+                (_, Some(&(t, d))) => {
+                    terms.push((t, b * d));
+                    j += 1;
+                }
+                (None, None) => break,
+            }
+        }
+        terms
+    }
+
+    pub fn add(&self, other: &Affine) -> Affine {
+        Self::from_form(self.center + other.center, self.combine(other, 1.0, 1.0))
+    }
+
+    pub fn sub(&self, other: &Affine) -> Affine {
+        Self::from_form(self.center - other.center, self.combine(other, 1.0, -1.0))
+    }
+
+    pub fn mul(&self, other: &Affine) -> Affine {
+        let linear = self.combine(other, other.center, self.center);
+
+        // What's left is (sum of c_i*e_i) * (sum of d_j*e_j), same symbols give e^2 which is never negative
+        let (mut low, mut high) = (0.0, 0.0);
+        let mut shared = 0.0;
+        for (s, c) in &self.terms {
+            if let Some((_, d)) = other.terms.iter().find(|(t, _)| t == s) {
+                let square = c * d;
+                if square > 0.0 { high += square } else { low += square }
+                shared += square.abs();
+            }
+        }
+        let cross = self.radius() * other.radius() - shared;
+        low -= cross;
+        high += cross;
+
+        let mut terms = linear;
+        let spread = (high - low) / 2.0;
+        if spread > 0.0 {
+            terms.push((fresh_symbol(), spread));
+        }
+        Self::from_form(self.center * other.center + (low + high) / 2.0, terms)
+    }
+
+    // 1 / x by the line that stays closest to it over the range, None when the range holds zero
+    pub fn recip(&self) -> Option<Affine> {
+        let (min, max) = self.range;
+        if min <= 0.0 && max >= 0.0 {
+            return None;
+        }
+        if max < 0.0 {
+            let negated = Self::from_form(-self.center, self.terms.iter().map(|(s, c)| (*s, -c)).collect());
+            let result = negated.clamp((-max, -min)).recip()?;
+            return Some(Self::from_form(-result.center, result.terms.iter().map(|(s, c)| (*s, -c)).collect()));
+        }
+
+        let slope = -1.0 / (max * max);
+        let (top, bottom) = (1.0 / min - slope * min, 1.0 / max - slope * max);
+        let mut terms: Vec<(usize, f64)> = self.terms.iter().map(|(s, c)| (*s, slope * c)).collect();
+        terms.push((fresh_symbol(), (top - bottom) / 2.0));
+        let center = slope * self.center + (top + bottom) / 2.0;
+        Some(Self::from_form(center, terms).clamp((1.0 / max, 1.0 / min)))
+    }
+}
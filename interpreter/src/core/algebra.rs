@@ -0,0 +1,667 @@
+use crate::core::value::{SKBool, Value};
+use crate::parser::ast::Expr;
+use crate::parser::lexer::{Token, TokenSpan};
+
+// Brings symbolic expressions to one canonical form: a sum of terms, each a number times a product of powers.
+// Like terms are collected and constants folded. Sums are only multiplied out when that leaves fewer terms:
+// expanding x * (1 - x) into x - x^2 would lose the dependency between its intervals
+
+#[derive(Clone)]
+struct Factor {
+    key: String,
+    base: Expr,
+    power: f64,
+}
+
+#[derive(Clone)]
+struct Term {
+    coeff: f64,
+    factors: Vec<Factor>, // sorted, no zero powers
+}
+
+type Sum = Vec<Term>;
+
+// Gives the formula of a symbolic variable by its name
+pub type Formulas<'a> = &'a dyn Fn(&str) -> Option<Expr>;
+
+pub fn simplify(expr: &Expr) -> Expr {
+    Simplifier::default().expr(expr)
+}
+
+// Whether both expressions simplify to the same thing
+pub fn equivalent(a: &Expr, b: &Expr) -> bool {
+    key(&simplify(a)) == key(&simplify(b))
+}
+
+#[derive(Default)]
+struct Simplifier<'a> {
+    unique: usize, // every unknown or interval literal is its own value, even when written the same
+    expand: bool,  // multiplies every sum out, to read the coefficients of a polynomial
+    inline: Option<Formulas<'a>>, // symbolic variables are worked into the result
+    visiting: Vec<String>,
+}
+
+impl Simplifier<'_> {
+    fn expr(&mut self, expr: &Expr) -> Expr {
+        if is_arithmetic(expr) {
+            let sum = self.sum(expr);
+            if !self.expand {
+                // a * (b + c) - a * b only cancels once it is multiplied out
+                let mut expanding = Simplifier { expand: true, unique: self.unique, inline: self.inline, visiting: self.visiting.clone() };
+                let expanded = expanding.sum(expr);
+                if expanded.len() < sum.len() {
+                    return build(expanded);
+                }
+            }
+            return build(sum);
+        }
+        match expr {
+            Expr::Grouping { expression } => self.expr(expression),
+            Expr::Binary { left, operator, right } => {
+                let (left, right) = (self.expr(left), self.expr(right));
+                fold(&left, &operator.token, &right).unwrap_or(Expr::Binary {
+                    left: Box::new(left),
+                    operator: operator.clone(),
+                    right: Box::new(right),
+                })
+            }
+            Expr::Unary { operator, right } => {
+                let right = self.expr(right);
+                match (&operator.token, literal_value(&right)) {
+                    (Token::Bang, Some(Value::Bool(b))) => value_literal(Value::Bool(crate::core::logic::not(b)))
+                        .unwrap_or(Expr::Unary { operator: operator.clone(), right: Box::new(right) }),
+                    _ => Expr::Unary { operator: operator.clone(), right: Box::new(right) },
+                }
+            }
+            Expr::Call { callee, paren, arguments } => Expr::Call {
+                callee: callee.clone(),
+                paren: paren.clone(),
+                arguments: arguments.iter().map(|a| self.expr(a)).collect(),
+            },
+            Expr::Interval { min, max, bracket } => Expr::Interval {
+                min: Box::new(self.expr(min)),
+                max: Box::new(self.expr(max)),
+                bracket: bracket.clone(),
+            },
+            Expr::OneOf { options, brace } => Expr::OneOf {
+                options: options.iter().map(|o| self.expr(o)).collect(),
+                brace: brace.clone(),
+            },
+            Expr::Conditional { condition, then_branch, else_branch } => {
+                let condition = self.expr(condition);
+                match literal_value(&condition) {
+                    Some(Value::Bool(SKBool::True)) => self.expr(then_branch),
+                    Some(Value::Bool(SKBool::False)) => self.expr(else_branch),
+                    _ => Expr::Conditional {
+                        condition: Box::new(condition),
+                        then_branch: Box::new(self.expr(then_branch)),
+                        else_branch: Box::new(self.expr(else_branch)),
+                    },
+                }
+            }
+            other => other.clone(),
+        }
+    }
+
+    fn sum(&mut self, expr: &Expr) -> Sum {
+        match expr {
+            Expr::Literal { value: TokenSpan { token: Token::Number(n), .. } } => constant(*n),
+            Expr::Grouping { expression } => self.sum(expression),
+            Expr::Unary { operator: TokenSpan { token: Token::Minus, .. }, right } => scale(self.sum(right), -1.0),
+            Expr::Binary { left, operator, right } if is_arithmetic(expr) => {
+                let (l, r) = (self.sum(left), self.sum(right));
+                match operator.token {
+                    Token::Plus => add(l, r),
+                    Token::Minus => add(l, scale(r, -1.0)),
+                    Token::Star if self.expand => expand(l, r),
+                    Token::Star => mul(l, r),
+                    Token::Slash => div(l, r, operator),
+                    _ => self.pow(l, r, operator),
+                }
+            }
+            Expr::Variable { name } if self.inline.is_some() => {
+                let name = name.token_to_string();
+                match self.inline.and_then(|inline| inline(&name)) {
+                    Some(formula) if !self.visiting.contains(&name) => {
+                        self.visiting.push(name);
+                        let sum = self.sum(&formula);
+                        self.visiting.pop();
+                        sum
+                    }
+                    _ => factor(expr.clone(), 1.0),
+                }
+            }
+            Expr::Literal { value: TokenSpan { token: Token::Unknown, .. } } | Expr::Interval { .. } | Expr::OneOf { .. } => {
+                let atom = self.expr(expr);
+                self.unique += 1;
+                let key = format!("{}#{}", key(&atom), self.unique);
+                vec![Term { coeff: 1.0, factors: vec![Factor { key, base: atom, power: 1.0 }] }]
+            }
+            _ => {
+                let atom = self.expr(expr);
+                match literal_value(&atom) {
+                    Some(Value::Number(n)) => constant(n),
+                    _ => factor(atom, 1.0),
+                }
+            }
+        }
+    }
+
+    fn pow(&self, base: Sum, exponent: Sum, operator: &TokenSpan) -> Sum {
+        match as_constant(&exponent) {
+            Some(n) if self.expand && base.len() > 1 && n.fract() == 0.0 && (1.0..=MAX_EXPANDED_POWER).contains(&n) => {
+                (1..n as usize).fold(base.clone(), |product, _| expand(product, base.clone()))
+            }
+            _ => pow(base, exponent, operator),
+        }
+    }
+}
+
+const MAX_EXPANDED_POWER: f64 = 16.0;
+
+fn is_arithmetic(expr: &Expr) -> bool {
+    match expr {
+        Expr::Literal { value } => matches!(value.token, Token::Number(_)),
+        Expr::Unary { operator, .. } => operator.token == Token::Minus,
+        Expr::Binary { operator, .. } => {
+            matches!(operator.token, Token::Plus | Token::Minus | Token::Star | Token::Slash | Token::Caret)
+        }
+        _ => false,
+    }
+}
+
+fn constant(n: f64) -> Sum {
+    if n == 0.0 { Vec::new() } else { vec![Term { coeff: n, factors: Vec::new() }] }
+}
+
+fn factor(base: Expr, power: f64) -> Sum {
+    vec![Term { coeff: 1.0, factors: vec![Factor { key: key(&base), base, power }] }]
+}
+
+fn as_constant(sum: &Sum) -> Option<f64> {
+    match sum.as_slice() {
+        [] => Some(0.0),
+        [term] if term.factors.is_empty() => Some(term.coeff),
+        _ => None,
+    }
+}
+
+// Terms with the same factors to the same powers only differ by their coefficient
+fn like(a: &Term, b: &Term) -> bool {
+    a.factors.len() == b.factors.len() && a.factors.iter().zip(&b.factors).all(|(f, g)| f.key == g.key && f.power == g.power)
+}
+
+fn add(mut a: Sum, b: Sum) -> Sum {
+    for term in b {
+        match a.iter_mut().find(|t| like(t, &term)) {
+            Some(t) => t.coeff += term.coeff,
+            None => a.push(term),
+        }
+    }
+    a.retain(|t| t.coeff != 0.0);
+    a
+}
+
+fn scale(sum: Sum, by: f64) -> Sum {
+    if by == 0.0 {
+        return Vec::new();
+    }
+    sum.into_iter().map(|t| Term { coeff: t.coeff * by, ..t }).collect()
+}
+
+// A sum of several terms becomes a single factor, so it can be multiplied without being expanded
+fn single(mut sum: Sum) -> Term {
+    match sum.len() {
+        0 => Term { coeff: 0.0, factors: Vec::new() },
+        1 => sum.remove(0),
+        _ => factor(build(sum), 1.0).remove(0),
+    }
+}
+
+fn mul_terms(a: Term, b: Term) -> Term {
+    let mut factors = a.factors;
+    for f in b.factors {
+        match factors.iter_mut().find(|g| g.key == f.key) {
+            Some(g) => g.power += f.power,
+            None => factors.push(f),
+        }
+    }
+    factors.retain(|f| f.power != 0.0);
+    // Names, then calls, then whole sums and literals: a * (b - c) rather than (b - c) * a
+    factors.sort_by_key(|f| (!matches!(f.base, Expr::Variable { .. }), is_compound(f), f.key.clone()));
+    Term { coeff: a.coeff * b.coeff, factors }
+}
+
+fn mul(a: Sum, b: Sum) -> Sum {
+    if let Some(n) = as_constant(&a) {
+        return scale(b, n);
+    }
+    if let Some(n) = as_constant(&b) {
+        return scale(a, n);
+    }
+    let term = mul_terms(single(a), single(b));
+    if term.coeff == 0.0 { Vec::new() } else { vec![term] }
+}
+
+// Every term of a times every term of b
+fn expand(a: Sum, b: Sum) -> Sum {
+    let mut product = Vec::new();
+    for t in &a {
+        let terms = b.iter().map(|u| mul_terms(t.clone(), u.clone())).collect();
+        product = add(product, terms);
+    }
+    product
+}
+
+fn div(a: Sum, b: Sum, operator: &TokenSpan) -> Sum {
+    match as_constant(&b) {
+        // Kept as it is, so resolving it still reports the division by zero
+        Some(0.0) => factor(binary(build(a), operator, build(b)), 1.0),
+        Some(n) => scale(a, 1.0 / n),
+        None => {
+            let divisor = single(b);
+            let inverse = Term {
+                coeff: 1.0 / divisor.coeff,
+                factors: divisor.factors.into_iter().map(|f| Factor { power: -f.power, ..f }).collect(),
+            };
+            mul(a, vec![inverse])
+        }
+    }
+}
+
+fn pow(base: Sum, exponent: Sum, operator: &TokenSpan) -> Sum {
+    let Some(n) = as_constant(&exponent) else {
+        return factor(binary(build(base), operator, build(exponent)), 1.0);
+    };
+    if let Some(b) = as_constant(&base) {
+        return match Value::Number(b).pow(&Value::Number(n)) {
+            Ok(Value::Number(result)) => constant(result),
+            _ => factor(binary(build(base), operator, build(exponent)), 1.0),
+        };
+    }
+    if n == 0.0 {
+        return constant(1.0);
+    }
+
+    // (x^a)^n is x^(a*n) for whole n, a fractional n would turn (x^2)^0.5 into x instead of |x|
+    let term = single(base);
+    let even = |p: f64| p.fract() == 0.0 && p % 2.0 == 0.0;
+    if n.fract() == 0.0 || (term.coeff > 0.0 && !term.factors.iter().any(|f| even(f.power))) {
+        let factors = term.factors.into_iter().map(|f| Factor { power: f.power * n, ..f }).collect();
+        return vec![Term { coeff: term.coeff.powf(n), factors }];
+    }
+    factor(binary(build(vec![term]), operator, build(exponent)), 1.0)
+}
+
+fn literal(n: f64) -> Expr {
+    Expr::Literal { value: span(Token::Number(n)) }
+}
+
+fn span(token: Token) -> TokenSpan {
+    TokenSpan { token, line: 0, column: 0 }
+}
+
+fn binary(left: Expr, operator: &TokenSpan, right: Expr) -> Expr {
+    Expr::Binary { left: Box::new(left), operator: operator.clone(), right: Box::new(right) }
+}
+
+fn degree(term: &Term) -> f64 {
+    term.factors.iter().map(|f| f.power).sum()
+}
+
+// Highest degree first, then by name, the constant last: 2 * x ^ 2 + 3 * x + 1
+fn build(mut sum: Sum) -> Expr {
+    sum.sort_by(|a, b| {
+        (a.factors.is_empty())
+            .cmp(&b.factors.is_empty())
+            .then(degree(b).total_cmp(&degree(a)))
+            .then_with(|| compound(a).cmp(&compound(b)))
+            .then_with(|| term_key(a).cmp(&term_key(b)))
+    });
+    // A positive term goes first when there is one, 1 - x reads better than -x + 1
+    if let Some(first) = sum.iter().position(|t| t.coeff > 0.0) {
+        let term = sum.remove(first);
+        sum.insert(0, term);
+    }
+
+    let mut terms = sum.into_iter();
+    let Some(first) = terms.next() else {
+        return literal(0.0);
+    };
+    let mut expr = build_term(first);
+    for term in terms {
+        let (operator, term) = if term.coeff < 0.0 {
+            (Token::Minus, Term { coeff: -term.coeff, ..term })
+        } else {
+            (Token::Plus, term)
+        };
+        expr = binary(expr, &span(operator), build_term(term));
+    }
+    expr
+}
+
+fn is_compound(factor: &Factor) -> bool {
+    !matches!(factor.base, Expr::Variable { .. } | Expr::Call { .. } | Expr::Get { .. })
+}
+
+fn compound(term: &Term) -> bool {
+    term.factors.iter().any(is_compound)
+}
+
+fn term_key(term: &Term) -> String {
+    let factors: Vec<String> = term.factors.iter().map(|f| format!("{}^{}", f.key, f.power)).collect();
+    factors.join("*")
+}
+
+fn build_term(term: Term) -> Expr {
+    let power = |f: Factor, p: f64| if p == 1.0 { f.base } else { binary(f.base, &span(Token::Caret), literal(p)) };
+    let product = |factors: Vec<Expr>| factors.into_iter().reduce(|a, b| binary(a, &span(Token::Star), b));
+
+    let (mut numerator, mut denominator) = (Vec::new(), Vec::new());
+    for f in term.factors {
+        if f.power < 0.0 {
+            let p = -f.power;
+            denominator.push(power(f, p));
+        } else {
+            let p = f.power;
+            numerator.push(power(f, p));
+        }
+    }
+    if term.coeff == -1.0 && !numerator.is_empty() {
+        // -x * y rather than -1 * x * y
+        let first = numerator.remove(0);
+        numerator.insert(0, Expr::Unary { operator: span(Token::Minus), right: Box::new(first) });
+    } else if term.coeff != 1.0 || numerator.is_empty() {
+        numerator.insert(0, literal(term.coeff));
+    }
+
+    let numerator = product(numerator).unwrap_or_else(|| literal(1.0));
+    match product(denominator) {
+        Some(denominator) => binary(numerator, &span(Token::Slash), denominator),
+        None => numerator,
+    }
+}
+
+fn literal_value(expr: &Expr) -> Option<Value> {
+    match expr {
+        Expr::Literal { value } => match &value.token {
+            Token::Number(n) => Some(Value::Number(*n)),
+            Token::String(s) => Some(Value::String(s.clone())),
+            Token::True => Some(Value::Bool(SKBool::True)),
+            Token::False => Some(Value::Bool(SKBool::False)),
+            Token::Partial => Some(Value::Bool(SKBool::Partial)),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+fn value_literal(value: Value) -> Option<Expr> {
+    let token = match value {
+        Value::Number(n) => Token::Number(n),
+        Value::String(s) => Token::String(s),
+        Value::Bool(SKBool::True) => Token::True,
+        Value::Bool(SKBool::False) => Token::False,
+        Value::Bool(SKBool::Partial) => Token::Partial,
+        _ => return None,
+    };
+    Some(Expr::Literal { value: span(token) })
+}
+
+// Operators on two literals give their value, anything that would be an error is left for resolve() to report
+fn fold(left: &Expr, op: &Token, right: &Expr) -> Option<Expr> {
+    let (l, r) = (literal_value(left)?, literal_value(right)?);
+    let result = match op {
+        Token::Percent => l.rem(&r),
+        Token::And | Token::Or => l.logic(&r, op),
+        Token::EqualEqual | Token::BangEqual | Token::Greater | Token::GreaterEqual | Token::Less | Token::LessEqual => {
+            l.compare(&r, op)
+        }
+        _ => return None,
+    };
+    value_literal(result.ok()?)
+}
+
+// A description of the expression that ignores where it was written
+fn key(expr: &Expr) -> String {
+    match expr {
+        Expr::Binary { left, operator, right } => format!("({} {:?} {})", key(left), operator.token, key(right)),
+        Expr::Literal { value } => format!("{:?}", value.token),
+        Expr::Variable { name } => name.token_to_string(),
+        Expr::Grouping { expression } => key(expression),
+        Expr::Unary { operator, right } => format!("{:?} {}", operator.token, key(right)),
+        Expr::Call { callee, arguments, .. } => {
+            let arguments: Vec<String> = arguments.iter().map(key).collect();
+            format!("{}({})", key(callee), arguments.join(", "))
+        }
+        Expr::Get { object, name } => format!("{}.{}", key(object), name.token_to_string()),
+        Expr::Index { object, index, .. } => format!("{}[{}]", key(object), key(index)),
+        Expr::Interval { min, max, .. } => format!("[{}..{}]", key(min), key(max)),
+        Expr::OneOf { options, .. } => {
+            let options: Vec<String> = options.iter().map(key).collect();
+            format!("{{{}}}", options.join(", "))
+        }
+        Expr::Conditional { condition, then_branch, else_branch } => {
+            format!("if {} {{ {} }} else {{ {} }}", key(condition), key(then_branch), key(else_branch))
+        }
+        other => format!("{:?}", other),
+    }
+}
+
+// The coefficients of expr as a polynomial in var, lowest degree first.
+// None when var shows up some other way, like in sin(x) or 1 / x
+pub fn polynomial(expr: &Expr, var: &str, inline: Formulas) -> Option<Vec<Expr>> {
+    let mut simplifier = Simplifier { expand: true, inline: Some(inline), ..Default::default() };
+    let mut coefficients: Vec<Sum> = Vec::new();
+    for mut term in simplifier.sum(expr) {
+        let degree = match term.factors.iter().position(|f| f.key == var) {
+            Some(i) => term.factors.remove(i).power,
+            None => 0.0,
+        };
+        if degree < 0.0 || degree.fract() != 0.0 || term.factors.iter().any(|f| mentions(&f.base, var)) {
+            return None;
+        }
+        let degree = degree as usize;
+        if coefficients.len() <= degree {
+            coefficients.resize(degree + 1, Vec::new());
+        }
+        coefficients[degree] = add(std::mem::take(&mut coefficients[degree]), vec![term]);
+    }
+    Some(coefficients.into_iter().map(build).collect())
+}
+
+// Swaps the variables `replace` has something for, what they are swapped with isn't looked into again
+pub fn substitute(expr: &Expr, replace: Formulas) -> Expr {
+    let sub = |e: &Expr| Box::new(substitute(e, replace));
+    match expr {
+        Expr::Variable { name } => replace(&name.token_to_string()).unwrap_or_else(|| expr.clone()),
+        Expr::Binary { left, operator, right } => Expr::Binary { left: sub(left), operator: operator.clone(), right: sub(right) },
+        Expr::Unary { operator, right } => Expr::Unary { operator: operator.clone(), right: sub(right) },
+        Expr::Grouping { expression } => Expr::Grouping { expression: sub(expression) },
+        Expr::Call { callee, paren, arguments } => Expr::Call {
+            callee: callee.clone(),
+            paren: paren.clone(),
+            arguments: arguments.iter().map(|a| substitute(a, replace)).collect(),
+        },
+        Expr::Interval { min, max, bracket } => Expr::Interval { min: sub(min), max: sub(max), bracket: bracket.clone() },
+        Expr::OneOf { options, brace } => Expr::OneOf {
+            options: options.iter().map(|o| substitute(o, replace)).collect(),
+            brace: brace.clone(),
+        },
+        Expr::Conditional { condition, then_branch, else_branch } => Expr::Conditional {
+            condition: sub(condition),
+            then_branch: sub(then_branch),
+            else_branch: sub(else_branch),
+        },
+        other => other.clone(),
+    }
+}
+
+fn mentions(expr: &Expr, var: &str) -> bool {
+    match expr {
+        Expr::Variable { name } => name.token_to_string() == var,
+        Expr::Binary { left, right, .. } => mentions(left, var) || mentions(right, var),
+        Expr::Unary { right, .. } => mentions(right, var),
+        Expr::Grouping { expression } => mentions(expression, var),
+        Expr::Call { callee, arguments, .. } => mentions(callee, var) || arguments.iter().any(|a| mentions(a, var)),
+        Expr::Get { object, .. } => mentions(object, var),
+        Expr::Index { object, index, .. } => mentions(object, var) || mentions(index, var),
+        Expr::Interval { min, max, .. } => mentions(min, var) || mentions(max, var),
+        Expr::OneOf { options, .. } => options.iter().any(|o| mentions(o, var)),
+        Expr::Conditional { condition, then_branch, else_branch } => {
+            mentions(condition, var) || mentions(then_branch, var) || mentions(else_branch, var)
+        }
+        _ => false,
+    }
+}
+
+// The derivative of expr with respect to var, `inline` gives the formula of the symbolic variables it uses
+// 'library' is how the math library can be reached where the derivative will be used, if it can
+pub fn derive(expr: &Expr, var: &str, inline: Formulas, library: Option<Expr>) -> Result<Expr, String> {
+    let derivative = Derivative { var, inline, library, visiting: Vec::new() }.of(expr)?;
+    Ok(simplify(&derivative))
+}
+
+struct Derivative<'a> {
+    var: &'a str,
+    inline: Formulas<'a>,
+    library: Option<Expr>,
+    visiting: Vec<String>,
+}
+
+impl Derivative<'_> {
+    fn of(&mut self, expr: &Expr) -> Result<Expr, String> {
+        let (star, plus, minus) = (span(Token::Star), span(Token::Plus), span(Token::Minus));
+        Ok(match expr {
+            Expr::Variable { name } => {
+                let name = name.token_to_string();
+                if name == self.var {
+                    return Ok(literal(1.0));
+                }
+                let Some(formula) = (self.inline)(&name) else {
+                    return Ok(literal(0.0));
+                };
+                if self.visiting.contains(&name) {
+                    return Err(format!("derive() can't follow {}, its formula uses itself", name));
+                }
+                self.visiting.push(name);
+                let derivative = self.of(&formula);
+                self.visiting.pop();
+                derivative?
+            }
+            Expr::Grouping { expression } => self.of(expression)?,
+            Expr::Unary { operator: TokenSpan { token: Token::Minus, .. }, right } => binary(literal(-1.0), &star, self.of(right)?),
+            Expr::Binary { left, operator, right } => {
+                let (dl, dr) = (self.of(left)?, self.of(right)?);
+                let (l, r) = (left.as_ref().clone(), right.as_ref().clone());
+                match operator.token {
+                    Token::Plus | Token::Minus => binary(dl, operator, dr),
+                    // (l * r)' = l' * r + l * r'
+                    Token::Star => binary(binary(dl, &star, r), &plus, binary(l, &star, dr)),
+                    // (l / r)' = (l' * r - l * r') / r^2
+                    Token::Slash => binary(
+                        binary(binary(dl, &star, r.clone()), &minus, binary(l, &star, dr)),
+                        operator,
+                        binary(r, &span(Token::Caret), literal(2.0)),
+                    ),
+                    Token::Caret if is_zero(&dr) => {
+                        // (l^n)' = n * l^(n - 1) * l'
+                        let lowered = binary(l, operator, binary(r.clone(), &minus, literal(1.0)));
+                        binary(binary(r, &star, lowered), &star, dl)
+                    }
+                    // (l^r)' = l^r * (r' * ln(l) + r * l' / l)
+                    Token::Caret => {
+                        let log = self.math("ln", l.clone())?;
+                        let inner = binary(binary(dr, &star, log), &plus, binary(binary(r.clone(), &star, dl), &span(Token::Slash), l.clone()));
+                        binary(binary(l, operator, r), &star, inner)
+                    }
+                    _ if is_zero(&dl) && is_zero(&dr) => literal(0.0),
+                    _ => return Err(format!("derive() can't differentiate the {} operator", operator.token_to_string())),
+                }
+            }
+            Expr::Call { callee, arguments, .. } => {
+                let derivatives = arguments.iter().map(|a| self.of(a)).collect::<Result<Vec<_>, _>>()?;
+                if derivatives.iter().all(is_zero) {
+                    return Ok(literal(0.0));
+                }
+                let name = match callee.as_ref() {
+                    Expr::Get { name, .. } | Expr::Variable { name } => name.token_to_string(),
+                    _ => "?".to_string(),
+                };
+                let slash = span(Token::Slash);
+                let square = |e: &Expr| binary(e.clone(), &span(Token::Caret), literal(2.0));
+                // the smaller (or larger) argument is the one that moves the result
+                let pick = |a: &Expr, op: Token, b: &Expr, da: &Expr, db: &Expr| Expr::Conditional {
+                    condition: Box::new(binary(a.clone(), &span(op), b.clone())),
+                    then_branch: Box::new(da.clone()),
+                    else_branch: Box::new(db.clone()),
+                };
+                let (u, du) = match (name.as_str(), arguments.as_slice(), derivatives.as_slice()) {
+                    // atan2(y, x)' = (x * y' - y * x') / (x^2 + y^2)
+                    ("atan2", [y, x], [dy, dx]) => {
+                        let numerator = binary(binary(x.clone(), &star, dy.clone()), &minus, binary(y.clone(), &star, dx.clone()));
+                        return Ok(binary(numerator, &slash, binary(square(x), &plus, square(y))));
+                    }
+                    ("min", [a, b], [da, db]) => return Ok(pick(a, Token::Less, b, da, db)),
+                    ("max", [a, b], [da, db]) => return Ok(pick(a, Token::Greater, b, da, db)),
+                    (_, [u], [du]) => (u.clone(), du.clone()),
+                    _ => return Err(format!("derive() doesn't know the derivative of {} with {} arguments", name, arguments.len())),
+                };
+                let f = |other: &str| call(callee, other, u.clone());
+                match name.as_str() {
+                    "sin" => binary(f("cos"), &star, du),
+                    "cos" => binary(binary(literal(-1.0), &star, f("sin")), &star, du),
+                    "tan" => binary(du, &slash, binary(f("cos"), &span(Token::Caret), literal(2.0))),
+                    "sqrt" => binary(du, &slash, binary(literal(2.0), &star, f("sqrt"))),
+                    "exp" => binary(f("exp"), &star, du),
+                    "ln" => binary(du, &slash, u.clone()),
+                    "log10" => binary(du, &slash, binary(u.clone(), &star, literal(10f64.ln()))),
+                    "log2" => binary(du, &slash, binary(u.clone(), &star, literal(2f64.ln()))),
+                    "abs" => binary(binary(u.clone(), &slash, f("abs")), &star, du),
+                    "deg" => binary(du, &star, literal(180.0 / std::f64::consts::PI)),
+                    "rad" => binary(du, &star, literal(std::f64::consts::PI / 180.0)),
+                    _ => return Err(format!("derive() doesn't know the derivative of {}", name)),
+                }
+            }
+            Expr::Conditional { condition, then_branch, else_branch } => Expr::Conditional {
+                condition: condition.clone(),
+                then_branch: Box::new(self.of(then_branch)?),
+                else_branch: Box::new(self.of(else_branch)?),
+            },
+            Expr::Literal { .. } | Expr::Get { .. } | Expr::Index { .. } => literal(0.0),
+            Expr::Interval { min, max, .. } if is_zero(&self.of(min)?) && is_zero(&self.of(max)?) => literal(0.0),
+            Expr::OneOf { options, .. } => {
+                for option in options {
+                    if !is_zero(&self.of(option)?) {
+                        return Err("derive() can't differentiate a set of values that changes with the variable".to_string());
+                    }
+                }
+                literal(0.0)
+            }
+            _ => return Err("derive() only works on arithmetic and math functions".to_string()),
+        })
+    }
+
+    // library.name(argument), for the functions a derivative needs that the formula didn't call itself
+    fn math(&self, name: &str, argument: Expr) -> Result<Expr, String> {
+        let Some(library) = &self.library else {
+            return Err(format!("derive() needs math.{} for this formula, import math first", name));
+        };
+        let callee = Expr::Get { object: Box::new(library.clone()), name: span(Token::Identifier(name.to_string())) };
+        Ok(call(&callee, name, argument))
+    }
+}
+
+fn is_zero(expr: &Expr) -> bool {
+    matches!(simplify(expr), Expr::Literal { value: TokenSpan { token: Token::Number(n), .. } } if n == 0.0)
+}
+
+// Calls another function the same way the callee was reached, sin(u) next to math.sin gives math.cos(u)
+fn call(callee: &Expr, name: &str, argument: Expr) -> Expr {
+    let name = span(Token::Identifier(name.to_string()));
+    let callee = match callee {
+        Expr::Get { object, .. } => Expr::Get { object: object.clone(), name },
+        _ => Expr::Variable { name },
+    };
+    Expr::Call { callee: Box::new(callee), paren: span(Token::RParen), arguments: vec![argument] }
+}
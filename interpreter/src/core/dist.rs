@@ -0,0 +1,121 @@
+use std::rc::Rc;
+
+use rand::Rng;
+
+use crate::core::logic;
+use crate::core::settings;
+use crate::core::value::SKBool;
+
+// A value only known through its probability distribution, kept as a fixed set of samples.
+// Arithmetic works sample by sample, so a distribution combined with itself stays correlated
+#[derive(Debug, Clone)]
+pub struct Dist {
+    samples: Rc<Vec<f64>>,
+    label: Option<String>, // how it was made, e.g. normal(21, 1.5), lost after arithmetic
+}
+
+impl PartialEq for Dist {
+    // Only the same draw is the same distribution, two normal(0, 1) are still independent
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.samples, &other.samples)
+    }
+}
+
+impl Dist {
+    fn draw(label: String, mut sample: impl FnMut(&mut rand::rngs::ThreadRng) -> f64) -> Self {
+        let mut rng = rand::rng();
+        let samples = (0..settings::current().samples.max(1)).map(|_| sample(&mut rng)).collect();
+        Self { samples: Rc::new(samples), label: Some(label) }
+    }
+
+    pub fn uniform(min: f64, max: f64) -> Self {
+        Self::draw(format!("uniform({}, {})", min, max), |rng| min + (max - min) * rng.random::<f64>())
+    }
+
+    pub fn normal(mean: f64, std: f64) -> Self {
+        // Box-Muller, 1 - u keeps the logarithm away from zero
+        Self::draw(format!("normal({}, {})", mean, std), |rng| {
+            let (u, v): (f64, f64) = (rng.random(), rng.random());
+            mean + std * (-2.0 * (1.0 - u).ln()).sqrt() * (std::f64::consts::TAU * v).cos()
+        })
+    }
+
+    pub fn triangular(min: f64, mode: f64, max: f64) -> Self {
+        let split = (mode - min) / (max - min);
+        Self::draw(format!("triangular({}, {}, {})", min, mode, max), |rng| {
+            let u: f64 = rng.random();
+            if u < split {
+                min + (u * (max - min) * (mode - min)).sqrt()
+            } else {
+                max - ((1.0 - u) * (max - min) * (max - mode)).sqrt()
+            }
+        })
+    }
+
+    // Each value with its weight, the weights don't need to add up to 1
+    pub fn discrete(options: Vec<(f64, f64)>) -> Self {
+        let total: f64 = options.iter().map(|(_, w)| w).sum();
+        let values: Vec<String> = options.iter().map(|(v, _)| v.to_string()).collect();
+        Self::draw(format!("discrete({})", values.join(", ")), |rng| {
+            let mut left = rng.random::<f64>() * total;
+            for (value, weight) in &options {
+                if left < *weight {
+                    return *value;
+                }
+                left -= weight;
+            }
+            options[options.len() - 1].0
+        })
+    }
+
+    pub fn map(&self, f: impl Fn(f64) -> f64) -> Dist {
+        Dist { samples: Rc::new(self.samples.iter().map(|x| f(*x)).collect()), label: None }
+    }
+
+    pub fn zip(&self, other: &Dist, f: impl Fn(f64, f64) -> f64) -> Dist {
+        let samples = self.samples.iter().zip(other.samples.iter()).map(|(x, y)| f(*x, *y)).collect();
+        Dist { samples: Rc::new(samples), label: None }
+    }
+
+    // How often the comparison holds, certain only when every sample agrees
+    pub fn chance(&self, other: impl Fn(usize) -> f64, op: &str) -> SKBool {
+        let hits = self
+            .samples
+            .iter()
+            .enumerate()
+            .map(|(i, x)| logic::compare_nums(*x, other(i), op) == SKBool::True)
+            .collect();
+        logic::from_hits(hits)
+    }
+
+    pub fn sample(&self, i: usize) -> f64 {
+        self.samples[i % self.samples.len()]
+    }
+
+    pub fn mean(&self) -> f64 {
+        self.samples.iter().sum::<f64>() / self.samples.len() as f64
+    }
+
+    pub fn std(&self) -> f64 {
+        let mean = self.mean();
+        let variance = self.samples.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / self.samples.len() as f64;
+        variance.sqrt()
+    }
+
+    // The value below which a fraction p of the samples fall
+    pub fn quantile(&self, p: f64) -> f64 {
+        let mut sorted = self.samples.to_vec();
+        sorted.sort_by(f64::total_cmp);
+        let index = (p.clamp(0.0, 1.0) * (sorted.len() - 1) as f64).round() as usize;
+        sorted[index]
+    }
+}
+
+impl std::fmt::Display for Dist {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match &self.label {
+            Some(label) => write!(f, "{}", label),
+            None => write!(f, "distribution (mean {:.3}, std {:.3})", self.mean(), self.std()),
+        }
+    }
+}
@@ -108,6 +108,11 @@ impl ErrorReporter {
         !self.errors.is_empty()
     }
 
+    // The file that is running right now, if there is one
+    pub fn source_name(&self) -> Option<String> {
+        self.context.as_ref().map(|context| context.name.clone())
+    }
+
     fn capture_context(&self, token: &TokenSpan) -> (Option<String>, Option<String>) {
         let Some(context) = &self.context else {
             return (None, None);
@@ -194,28 +199,29 @@ impl fmt::Display for Error {
             }
         }
 
-        if let Some(line_text) = &self.line_text {
-            if self.token.line > 0 && self.token.column > 0 {
-                let width = std::cmp::max(4, self.token.line.to_string().len());
-                let gutter = format!("{}{:>width$} |{}", COLOR_BLUE, "", COLOR_RESET, width = width);
-                let prefix = format!("{}{:>width$} |{} ", COLOR_BLUE, self.token.line, COLOR_RESET, width = width);
-                let marker_prefix = format!("{}{:>width$} |{} ", COLOR_BLUE, "", COLOR_RESET, width = width);
-                let caret_len = std::cmp::max(1, self.token.display_len());
-                let spaces = " ".repeat(self.token.column.saturating_sub(1));
-                let carets = "^".repeat(caret_len);
-                write!(
-                    f,
-                    "\n{}\n{}{}\n{}{}{}{}",
-                    gutter,
-                    prefix,
-                    line_text,
-                    marker_prefix,
-                    spaces,
-                    COLOR_RED,
-                    carets
-                )?;
-                write!(f, "{}", COLOR_RESET)?;
-            }
+        if let Some(line_text) = &self.line_text
+            && self.token.line > 0
+            && self.token.column > 0
+        {
+            let width = std::cmp::max(4, self.token.line.to_string().len());
+            let gutter = format!("{}{:>width$} |{}", COLOR_BLUE, "", COLOR_RESET, width = width);
+            let prefix = format!("{}{:>width$} |{} ", COLOR_BLUE, self.token.line, COLOR_RESET, width = width);
+            let marker_prefix = format!("{}{:>width$} |{} ", COLOR_BLUE, "", COLOR_RESET, width = width);
+            let caret_len = std::cmp::max(1, self.token.display_len());
+            let spaces = " ".repeat(self.token.column.saturating_sub(1));
+            let carets = "^".repeat(caret_len);
+            write!(
+                f,
+                "\n{}\n{}{}\n{}{}{}{}",
+                gutter,
+                prefix,
+                line_text,
+                marker_prefix,
+                spaces,
+                COLOR_RED,
+                carets
+            )?;
+            write!(f, "{}", COLOR_RESET)?;
         }
 
         Ok(())
@@ -242,13 +248,14 @@ impl fmt::Display for Warning {
             }
         }
 
-        if let Some(line_text) = &self.line_text {
-            if self.token.line > 0 && self.token.column > 0 {
-                let width = std::cmp::max(4, self.token.line.to_string().len());
-                let gutter = format!("{}{:>width$} |{}", COLOR_BLUE, "", COLOR_RESET, width = width);
-                let prefix = format!("{}{:>width$} |{} ", COLOR_BLUE, self.token.line, COLOR_RESET, width = width);
-                write!(f, "\n{}\n{}{}", gutter, prefix, line_text)?;
-            }
+        if let Some(line_text) = &self.line_text
+            && self.token.line > 0
+            && self.token.column > 0
+        {
+            let width = std::cmp::max(4, self.token.line.to_string().len());
+            let gutter = format!("{}{:>width$} |{}", COLOR_BLUE, "", COLOR_RESET, width = width);
+            let prefix = format!("{}{:>width$} |{} ", COLOR_BLUE, self.token.line, COLOR_RESET, width = width);
+            write!(f, "\n{}\n{}{}", gutter, prefix, line_text)?;
         }
 
         Ok(())
@@ -1,9 +1,16 @@
+use std::rc::Rc;
+
 use crate::core::value::SKBool;
 
+// Weighted booleans from the same samples are combined sample by sample, any others are taken to be independent
 pub fn and(a: SKBool, b: SKBool) -> SKBool {
     match (a, b) {
         (SKBool::False, _) | (_, SKBool::False) => SKBool::False,
-        (SKBool::True, SKBool::True) => SKBool::True,
+        (SKBool::True, other) | (other, SKBool::True) => other,
+        (SKBool::Weighted(_, Some(x)), SKBool::Weighted(_, Some(y))) if x.len() == y.len() => {
+            from_hits(x.iter().zip(y.iter()).map(|(a, b)| *a && *b).collect())
+        }
+        (SKBool::Weighted(p, _), SKBool::Weighted(q, _)) => SKBool::Weighted(p * q, None),
         _ => SKBool::Partial,
     }
 }
@@ -11,7 +18,11 @@ pub fn and(a: SKBool, b: SKBool) -> SKBool {
 pub fn or(a: SKBool, b: SKBool) -> SKBool {
     match (a, b) {
         (SKBool::True, _) | (_, SKBool::True) => SKBool::True,
-        (SKBool::False, SKBool::False) => SKBool::False,
+        (SKBool::False, other) | (other, SKBool::False) => other,
+        (SKBool::Weighted(_, Some(x)), SKBool::Weighted(_, Some(y))) if x.len() == y.len() => {
+            from_hits(x.iter().zip(y.iter()).map(|(a, b)| *a || *b).collect())
+        }
+        (SKBool::Weighted(p, _), SKBool::Weighted(q, _)) => SKBool::Weighted(p + q - p * q, None),
         _ => SKBool::Partial,
     }
 }
@@ -21,9 +32,56 @@ pub fn not(a: SKBool) -> SKBool {
         SKBool::True => SKBool::False,
         SKBool::False => SKBool::True,
         SKBool::Partial => SKBool::Partial,
+        SKBool::Weighted(p, hits) => SKBool::Weighted(1.0 - p, hits.map(|h| Rc::new(h.iter().map(|x| !x).collect()))),
+    }
+}
+
+// One of two things happened, only certain if both agree
+pub fn either(a: SKBool, b: SKBool) -> SKBool {
+    match (a, b) {
+        (SKBool::True, SKBool::True) => SKBool::True,
+        (SKBool::False, SKBool::False) => SKBool::False,
+        (SKBool::Weighted(p, x), SKBool::Weighted(q, y)) if p == q => SKBool::Weighted(p, if x == y { x } else { None }),
+        _ => SKBool::Partial,
     }
 }
 
+// A comparison done on every sample of a distribution, certain only when every sample agrees
+pub fn from_hits(hits: Vec<bool>) -> SKBool {
+    match hits.iter().filter(|hit| **hit).count() {
+        0 => SKBool::False,
+        n if n == hits.len() => SKBool::True,
+        n => SKBool::Weighted(n as f64 / hits.len() as f64, Some(Rc::new(hits))),
+    }
+}
+
+// How likely x > y is when x is spread evenly over a and y over b, only for finite ranges
+pub fn chance_greater((a_min, a_max): (f64, f64), (b_min, b_max): (f64, f64)) -> Option<f64> {
+    if ![a_min, a_max, b_min, b_max].iter().all(|n| n.is_finite()) {
+        return None;
+    }
+    if a_min == a_max {
+        return Some(if b_min == b_max { if a_min > b_min { 1.0 } else { 0.0 } } else { ((a_min - b_min) / (b_max - b_min)).clamp(0.0, 1.0) });
+    }
+    if b_min == b_max {
+        return Some(((a_max - b_min) / (a_max - a_min)).clamp(0.0, 1.0));
+    }
+
+    // Averages the share of x above each y, it is 1 while y is under a_min and then falls in a straight line
+    let always = (a_min.min(b_max) - b_min).max(0.0);
+    let (from, to) = (b_min.max(a_min), b_max.min(a_max));
+    let falling = if to > from {
+        ((a_max - from).powi(2) - (a_max - to).powi(2)) / (2.0 * (a_max - a_min))
+    } else {
+        0.0
+    };
+    Some((always + falling) / (b_max - b_min))
+}
+
+fn weigh(chance: Option<f64>) -> SKBool {
+    chance.map_or(SKBool::Partial, |p| SKBool::Weighted(p, None))
+}
+
 pub fn compare_nums(a: f64, b: f64, op: &str) -> SKBool {
     let res = match op {
         "==" => a == b,
@@ -37,27 +95,28 @@ pub fn compare_nums(a: f64, b: f64, op: &str) -> SKBool {
     if res { SKBool::True } else { SKBool::False }
 }
 
+// Orderings that can go either way are weighted as if both intervals were spread evenly
 pub fn compare_intervals(min1: f64, max1: f64, min2: f64, max2: f64, op: &str) -> SKBool {
     match op {
         ">" => {
             if min1 > max2 { SKBool::True }
             else if max1 <= min2 { SKBool::False }
-            else { SKBool::Partial }
+            else { weigh(chance_greater((min1, max1), (min2, max2))) }
         }
         "<" => {
             if max1 < min2 { SKBool::True }
             else if min1 >= max2 { SKBool::False }
-            else { SKBool::Partial }
+            else { weigh(chance_greater((min2, max2), (min1, max1))) }
         }
         ">=" => {
             if min1 >= max2 { SKBool::True }
             else if max1 < min2 { SKBool::False }
-            else { SKBool::Partial }
+            else { weigh(chance_greater((min1, max1), (min2, max2))) }
         }
         "<=" => {
             if max1 <= min2 { SKBool::True }
             else if min1 > max2 { SKBool::False }
-            else { SKBool::Partial }
+            else { weigh(chance_greater((min2, max2), (min1, max1))) }
         }
         "==" => {
             if min1 == max1 && min2 == max2 && min1 == min2 { SKBool::True }
@@ -71,4 +130,46 @@ pub fn compare_intervals(min1: f64, max1: f64, min2: f64, max2: f64, op: &str) -
         }
         _ => SKBool::Partial,
     }
-}
\ No newline at end of file
+}
+
+// An end point of a range, the flag is true when the point itself is left out
+pub type Bound = (f64, bool);
+
+pub fn flip(op: &str) -> &str {
+    match op {
+        ">" => "<",
+        "<" => ">",
+        ">=" => "<=",
+        "<=" => ">=",
+        other => other,
+    }
+}
+
+// Same as compare_intervals, but either end of a range can be open (x > 0 is (0..inf))
+pub fn compare_ranges(a: (Bound, Bound), b: (Bound, Bound), op: &str) -> SKBool {
+    let greater = |(x_min, x_max): (Bound, Bound), (y_min, y_max): (Bound, Bound)| {
+        if x_min.0 > y_max.0 || (x_min.0 == y_max.0 && (x_min.1 || y_max.1)) { SKBool::True }
+        else if x_max.0 <= y_min.0 { SKBool::False }
+        else { SKBool::Partial }
+    };
+    let greater_equal = |(x_min, x_max): (Bound, Bound), (y_min, y_max): (Bound, Bound)| {
+        if x_min.0 >= y_max.0 { SKBool::True }
+        else if x_max.0 < y_min.0 || (x_max.0 == y_min.0 && (x_max.1 || y_min.1)) { SKBool::False }
+        else { SKBool::Partial }
+    };
+    let is_point = |(min, max): (Bound, Bound)| min.0 == max.0 && !min.1 && !max.1;
+
+    match op {
+        ">" => greater(a, b),
+        "<" => greater(b, a),
+        ">=" => greater_equal(a, b),
+        "<=" => greater_equal(b, a),
+        "==" => {
+            if is_point(a) && is_point(b) && a.0.0 == b.0.0 { SKBool::True }
+            else if greater(a, b) == SKBool::True || greater(b, a) == SKBool::True { SKBool::False }
+            else { SKBool::Partial }
+        }
+        "!=" => not(compare_ranges(a, b, "==")),
+        _ => SKBool::Partial,
+    }
+}
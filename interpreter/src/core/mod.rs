@@ -1,3 +1,7 @@
 pub mod value;
 pub mod logic;
-pub mod error;
\ No newline at end of file
+pub mod error;
+pub mod settings;
+pub mod affine;
+pub mod dist;
+pub mod algebra;
@@ -0,0 +1,34 @@
+use std::cell::Cell;
+
+// Per interpreter options, core code reads them from here instead of having them passed through every call
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Settings {
+    pub strict_division: bool, // dividing by an interval containing zero is an error
+    pub outward_rounding: bool, // interval bounds are rounded away from the result so it is always enclosed
+    pub affine: bool, // interval literals become affine forms, see core::affine
+    pub samples: usize, // how many samples every distribution is drawn with
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            strict_division: false,
+            outward_rounding: false,
+            affine: false,
+            samples: 10_000,
+        }
+    }
+}
+
+thread_local! {
+    static CURRENT: Cell<Settings> = Cell::new(Settings::default());
+}
+
+pub fn current() -> Settings {
+    CURRENT.with(|c| c.get())
+}
+
+// Installs new settings and hands back the ones that were active
+pub fn replace(settings: Settings) -> Settings {
+    CURRENT.with(|c| c.replace(settings))
+}
@@ -1,18 +1,39 @@
 use core::fmt;
-use crate::parser::ast::{Expr, Parameter, Stmt};
+use crate::parser::ast::{Expr, Field, Parameter, Stmt};
 use crate::parser::lexer::{Token, TokenSpan};
 use crate::core::logic;
+use crate::core::algebra;
+use crate::core::affine::Affine;
+use crate::core::dist::Dist;
 use crate::core::error::Error;
+use crate::core::settings;
 
 use crate::evaluator::env::Environment;
+use std::collections::HashMap;
 use std::rc::Rc;
 use std::cell::RefCell;
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone)]
 pub enum SKBool {
     True,
     False,
-    Partial
+    Partial,
+    Weighted(f64, Option<Hits>), // partial, with an estimate of how likely it is to be true
+}
+
+// Which samples of a distribution a comparison held for
+pub type Hits = Rc<Vec<bool>>;
+
+impl PartialEq for SKBool {
+    // How likely it is doesn't change that it is partial
+    fn eq(&self, other: &Self) -> bool {
+        matches!(
+            (self, other),
+            (SKBool::True, SKBool::True)
+                | (SKBool::False, SKBool::False)
+                | (SKBool::Partial | SKBool::Weighted(..), SKBool::Partial | SKBool::Weighted(..))
+        )
+    }
 }
 
 pub type NativeFn = fn(Vec<Value>, TokenSpan, &mut crate::evaluator::eval::Evaluator) -> Result<Value, Error>;
@@ -23,6 +44,99 @@ pub struct Function {
     pub body: Vec<Stmt>,
     pub closure: Rc<RefCell<Environment>>, 
     pub is_public: bool,
+    pub owner: Option<Rc<StructDef>>, // the struct whose private members it can reach
+}
+
+#[derive(Debug)]
+pub struct StructDef {
+    pub name: String,
+    pub fields: Vec<Field>,
+    pub methods: RefCell<HashMap<String, Function>>,
+    pub closure: Rc<RefCell<Environment>>,
+}
+
+#[derive(Debug)]
+pub struct Instance {
+    pub def: Rc<StructDef>,
+    pub fields: Vec<(String, Value)>,
+}
+
+// What an 'unknown x > 0' declaration knows about its value
+#[derive(Debug, Clone, PartialEq)]
+pub struct Bounds {
+    pub min: logic::Bound,
+    pub max: logic::Bound,
+    pub modulus: Option<(f64, f64)>, // x % m == r
+    pub excluded: Vec<f64>,
+    pub constraint: Box<Expr>, // kept around to display it
+}
+
+impl Bounds {
+    pub fn new(constraint: Expr) -> Self {
+        Self {
+            min: (f64::NEG_INFINITY, true),
+            max: (f64::INFINITY, true),
+            modulus: None,
+            excluded: Vec::new(),
+            constraint: Box::new(constraint),
+        }
+    }
+
+    pub fn allows(&self, n: f64) -> bool {
+        let above = n > self.min.0 || (n == self.min.0 && !self.min.1);
+        let below = n < self.max.0 || (n == self.max.0 && !self.max.1);
+        let fits_modulus = self.modulus.is_none_or(|(m, r)| n.rem_euclid(m) == r);
+        above && below && fits_modulus && !self.excluded.contains(&n)
+    }
+
+    // Whether at least one value is left, only checks the range and the modulus
+    pub fn is_satisfiable(&self) -> bool {
+        let (lo, hi) = (self.min.0, self.max.0);
+        if lo > hi || (lo == hi && (self.min.1 || self.max.1)) {
+            return false;
+        }
+        match self.modulus {
+            Some((m, r)) if lo.is_finite() && hi.is_finite() => {
+                let first = lo + (r - lo.rem_euclid(m)).rem_euclid(m);
+                let first = if first == lo && self.min.1 { first + m } else { first };
+                first < hi || (first == hi && !self.max.1)
+            }
+            _ => true,
+        }
+    }
+
+    // 1 / x as an interval, only when the range stays on one side of zero, 'x > 0' gives [0..inf]
+    // widening first would turn the open bound at zero into a closed one and divide by it
+    pub fn reciprocal(&self) -> Option<Value> {
+        let ((min, min_open), (max, max_open)) = (self.min, self.max);
+        let positive = min > 0.0 || (min == 0.0 && min_open);
+        let negative = max < 0.0 || (max == 0.0 && max_open);
+        if !positive && !negative {
+            return None;
+        }
+        let recip = |n: f64| if n == 0.0 { if positive { f64::INFINITY } else { f64::NEG_INFINITY } } else { 1.0 / n };
+        let (a, b) = (recip(min), recip(max));
+        Some(Value::Interval(a.min(b), a.max(b)))
+    }
+
+    fn compare(&self, other: &Value, op: &str) -> SKBool {
+        let range = match other {
+            Value::Number(n) => ((*n, false), (*n, false)),
+            Value::Interval(min, max) => ((*min, false), (*max, false)),
+            Value::Bounded(b) => (b.min, b.max),
+            _ => return SKBool::Partial,
+        };
+
+        // a number inside the range can still be ruled out, 3 is never even
+        if let Value::Number(n) = other && !self.allows(*n) {
+            match op {
+                "==" => return SKBool::False,
+                "!=" => return SKBool::True,
+                _ => {}
+            }
+        }
+        logic::compare_ranges((self.min, self.max), range, op)
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -31,14 +145,24 @@ pub enum Value {
     String(String),
     Bool(SKBool),
     Interval(f64, f64),
+    Affine(Affine), // an interval that keeps track of where its uncertainty came from, see core::affine
+    Dist(Dist), // a probability distribution, see core::dist
+    Union(Vec<(f64, f64)>), // sorted, disjoint pieces, always more than one
+    Empty,
     Unknown,
+    Bounded(Bounds),
+    OneOf(Vec<Value>), // exactly one of a few options
     Symbolic {
         expression: Box<Expr>,
         is_quiet: bool,
     },
+    List(Rc<RefCell<Vec<Value>>>),
+    Map(Rc<RefCell<Vec<(String, Value)>>>),
     NativeFn(NativeFn),
     Function(Function),
     Module(Rc<RefCell<Environment>>),
+    Struct(Rc<StructDef>),
+    Instance(Rc<RefCell<Instance>>),
     None,
 }
 
@@ -49,8 +173,26 @@ impl PartialEq for Value {
             (Value::String(a), Value::String(b)) => a == b,
             (Value::Bool(a), Value::Bool(b)) => a == b,
             (Value::Interval(a_min, a_max), Value::Interval(b_min, b_max)) => a_min == b_min && a_max == b_max,
+            (Value::Affine(a), Value::Affine(b)) => a == b,
+            (Value::Dist(a), Value::Dist(b)) => a == b,
+            (Value::Union(a), Value::Union(b)) => a == b,
+            (Value::Empty, Value::Empty) => true,
             (Value::Unknown, Value::Unknown) => true,
-            (Value::Symbolic { expression: e1, is_quiet: q1 }, Value::Symbolic { expression: e2, is_quiet: q2 }) => e1 == e2 && q1 == q2,
+            (Value::Bounded(a), Value::Bounded(b)) => a == b,
+            (Value::OneOf(a), Value::OneOf(b)) => a.len() == b.len() && a.iter().all(|v| b.contains(v)),
+            (Value::List(a), Value::List(b)) => *a.borrow() == *b.borrow(),
+            (Value::Map(a), Value::Map(b)) => {
+                let (a, b) = (a.borrow(), b.borrow());
+                a.len() == b.len() && a.iter().all(|(k, v)| b.iter().any(|(k2, v2)| k == k2 && v == v2))
+            }
+            (Value::Symbolic { expression: e1, is_quiet: q1 }, Value::Symbolic { expression: e2, is_quiet: q2 }) => {
+                q1 == q2 && (e1 == e2 || algebra::equivalent(e1, e2))
+            }
+            (Value::Struct(a), Value::Struct(b)) => Rc::ptr_eq(a, b),
+            (Value::Instance(a), Value::Instance(b)) => {
+                let (a, b) = (a.borrow(), b.borrow());
+                Rc::ptr_eq(&a.def, &b.def) && a.fields == b.fields
+            }
             (Value::None, Value::None) => true,
             _ => false,
         }
@@ -62,17 +204,287 @@ impl Value {
         matches!(self, Value::Symbolic { .. } | Value::Unknown)
     }
 
+    // Arithmetic on a constrained unknown only keeps its range
+    pub fn widen(self) -> Value {
+        match self {
+            Value::Bounded(b) if b.min.0.is_infinite() && b.max.0.is_infinite() => Value::Unknown,
+            Value::Bounded(b) => Value::Interval(b.min.0, b.max.0),
+            other => other,
+        }
+    }
+
+    // Everything but arithmetic sees an affine value as the interval it covers
+    pub fn plain(self) -> Value {
+        match self {
+            Value::Affine(a) => Value::Interval(a.range().0, a.range().1),
+            other => other,
+        }
+    }
+
+    // Nudges every finite bound one step outward when outward rounding is on,
+    // f64 operations round to nearest so this is what keeps the true result inside
+    pub fn outward(self) -> Value {
+        if !settings::current().outward_rounding {
+            return self;
+        }
+        let down = |n: f64| if n.is_finite() { n.next_down() } else { n };
+        let up = |n: f64| if n.is_finite() { n.next_up() } else { n };
+        match self {
+            Value::Interval(min, max) => Value::Interval(down(min), up(max)),
+            Value::Union(pieces) => Value::from_pieces(pieces.into_iter().map(|(min, max)| (down(min), up(max))).collect()),
+            other => other,
+        }
+    }
+
+    // Flattens nested options and drops repeated ones, a single option is just that value
+    pub fn one_of(options: Vec<Value>) -> Value {
+        let mut flat: Vec<Value> = Vec::new();
+        for option in options {
+            let inner = match option {
+                Value::OneOf(inner) => inner,
+                other => vec![other],
+            };
+            for value in inner {
+                if !flat.contains(&value) {
+                    flat.push(value);
+                }
+            }
+        }
+
+        // one of true and false is just partial
+        if flat.iter().all(|v| matches!(v, Value::Bool(_))) && let Some(Value::Bool(first)) = flat.first() {
+            let joined = flat.iter().fold(first.clone(), |acc, v| match v {
+                Value::Bool(b) => logic::either(acc, b.clone()),
+                _ => acc,
+            });
+            return Value::Bool(joined);
+        }
+
+        if flat.len() == 1 { flat.remove(0) } else { Value::OneOf(flat) }
+    }
+
+    // Sorts and joins overlapping pieces, then picks the simplest value that holds them
+    pub fn from_pieces(mut pieces: Vec<(f64, f64)>) -> Value {
+        pieces.sort_by(|a, b| a.0.total_cmp(&b.0));
+        let mut joined: Vec<(f64, f64)> = Vec::new();
+        for (min, max) in pieces {
+            match joined.last_mut() {
+                Some(last) if min <= last.1 => last.1 = last.1.max(max),
+                _ => joined.push((min, max)),
+            }
+        }
+
+        match joined.len() {
+            0 => Value::Empty,
+            1 => Value::Interval(joined[0].0, joined[0].1),
+            _ => Value::Union(joined),
+        }
+    }
+
+    // The value as a set of closed pieces, a number is a piece of its own
+    pub fn pieces(&self) -> Option<Vec<(f64, f64)>> {
+        match self {
+            Value::Number(n) => Some(vec![(*n, *n)]),
+            Value::Interval(min, max) => Some(vec![(*min, *max)]),
+            Value::Affine(a) => Some(vec![a.range()]),
+            Value::Union(pieces) => Some(pieces.clone()),
+            Value::Empty => Some(Vec::new()),
+            _ => None,
+        }
+    }
+
+    pub fn union(&self, other: &Value) -> Option<Value> {
+        let mut pieces = self.pieces()?;
+        pieces.extend(other.pieces()?);
+        Some(Value::from_pieces(pieces))
+    }
+
+    pub fn intersection(&self, other: &Value) -> Option<Value> {
+        let (a, b) = (self.pieces()?, other.pieces()?);
+        let mut pieces = Vec::new();
+        for (min1, max1) in &a {
+            for (min2, max2) in &b {
+                let (low, high) = (min1.max(*min2), max1.min(*max2));
+                if low <= high {
+                    pieces.push((low, high));
+                }
+            }
+        }
+        Some(Value::from_pieces(pieces))
+    }
+
+    // Boundaries are shared with the original, pieces are always closed
+    pub fn complement(&self) -> Option<Value> {
+        let mut pieces = Vec::new();
+        let mut start = f64::NEG_INFINITY;
+        for (min, max) in Value::from_pieces(self.pieces()?).pieces()? {
+            if min > start {
+                pieces.push((start, min));
+            }
+            start = max;
+        }
+        if start < f64::INFINITY {
+            pieces.push((start, f64::INFINITY));
+        }
+        Some(Value::from_pieces(pieces))
+    }
+
+    pub fn contains(&self, other: &Value) -> Option<SKBool> {
+        let pieces = self.pieces()?;
+        let inside = other.pieces()?.iter().all(|(low, high)| {
+            pieces.iter().any(|(min, max)| min <= low && high <= max)
+        });
+
+        if inside {
+            Some(SKBool::True)
+        } else if self.intersection(other)? == Value::Empty {
+            Some(SKBool::False)
+        } else {
+            Some(SKBool::Partial)
+        }
+    }
+
+    // Applies an operation piece by piece and joins the results, for unions and empty values
+    fn piecewise(&self, other: &Value, op: fn(&Value, &Value) -> Result<Value, Error>) -> Result<Option<Value>, Error> {
+        if !matches!(self, Value::Union(_) | Value::Empty) && !matches!(other, Value::Union(_) | Value::Empty) {
+            return Ok(None);
+        }
+        let (Some(a), Some(b)) = (self.pieces(), other.pieces()) else {
+            return Ok(None);
+        };
+
+        let as_value = |(min, max): (f64, f64)| if min == max { Value::Number(min) } else { Value::Interval(min, max) };
+        let mut pieces = Vec::new();
+        for p in &a {
+            for q in &b {
+                pieces.extend(op(&as_value(*p), &as_value(*q))?.pieces().unwrap_or_default());
+            }
+        }
+        Ok(Some(Value::from_pieces(pieces)))
+    }
+
+    // The smallest interval around a numeric value, if there is one
+    pub fn hull(&self) -> Option<Value> {
+        match self {
+            Value::Number(_) | Value::Interval(..) => Some(self.clone()),
+            Value::Affine(_) => Some(self.clone().plain()),
+            Value::Union(pieces) => Some(Value::Interval(pieces[0].0, pieces[pieces.len() - 1].1)),
+            Value::OneOf(options) => {
+                let mut bounds = (f64::INFINITY, f64::NEG_INFINITY);
+                for option in options {
+                    match option.hull()? {
+                        Value::Number(n) => bounds = (bounds.0.min(n), bounds.1.max(n)),
+                        Value::Interval(min, max) => bounds = (bounds.0.min(min), bounds.1.max(max)),
+                        _ => return None,
+                    }
+                }
+                Some(Value::Interval(bounds.0, bounds.1))
+            }
+            _ => None,
+        }
+    }
+
+    pub fn list(items: Vec<Value>) -> Value {
+        Value::List(Rc::new(RefCell::new(items)))
+    }
+
+    pub fn map(entries: Vec<(String, Value)>) -> Value {
+        Value::Map(Rc::new(RefCell::new(entries)))
+    }
+
+    // What to store inside container, lists and maps are shared so push(xs, xs) would make xs hold itself,
+    // a value that reaches its container is copied instead
+    pub fn stored_in(&self, container: &Value) -> Value {
+        if self.reaches(container) { self.deep_copy() } else { self.clone() }
+    }
+
+    fn reaches(&self, container: &Value) -> bool {
+        let same = match (self, container) {
+            (Value::List(a), Value::List(b)) => Rc::ptr_eq(a, b),
+            (Value::Map(a), Value::Map(b)) => Rc::ptr_eq(a, b),
+            (Value::Instance(a), Value::Instance(b)) => Rc::ptr_eq(a, b),
+            _ => false,
+        };
+        same || match self {
+            Value::List(items) => items.borrow().iter().any(|v| v.reaches(container)),
+            Value::Map(entries) => entries.borrow().iter().any(|(_, v)| v.reaches(container)),
+            Value::Instance(instance) => instance.borrow().fields.iter().any(|(_, v)| v.reaches(container)),
+            _ => false,
+        }
+    }
+
+    fn deep_copy(&self) -> Value {
+        match self {
+            Value::List(items) => Value::list(items.borrow().iter().map(Value::deep_copy).collect()),
+            Value::Map(entries) => Value::map(entries.borrow().iter().map(|(k, v)| (k.clone(), v.deep_copy())).collect()),
+            Value::Instance(instance) => {
+                let instance = instance.borrow();
+                Value::Instance(Rc::new(RefCell::new(Instance {
+                    def: instance.def.clone(),
+                    fields: instance.fields.iter().map(|(k, v)| (k.clone(), v.deep_copy())).collect(),
+                })))
+            }
+            other => other.clone(),
+        }
+    }
+
+    // How tightly an operator binds when printed, so only the parentheses it needs are written
+    fn binding(op: &Token) -> u8 {
+        match op {
+            Token::Or => 1,
+            Token::And => 2,
+            Token::EqualEqual | Token::BangEqual => 3,
+            Token::Greater | Token::GreaterEqual | Token::Less | Token::LessEqual => 4,
+            Token::Plus | Token::Minus => 5,
+            Token::Star | Token::Slash | Token::Percent => 6,
+            _ => 7,
+        }
+    }
+
+    fn precedence(expr: &Expr) -> u8 {
+        match expr {
+            Expr::Binary { operator, .. } => Self::binding(&operator.token),
+            Expr::Literal { value: TokenSpan { token: Token::Number(n), .. } } if *n < 0.0 => 8,
+            Expr::Unary { .. } => 8,
+            _ => 9,
+        }
+    }
+
+    fn format_operand(expr: &Expr, parent: &Token, right: bool) -> String {
+        let parent_precedence = Self::binding(parent);
+        let precedence = Self::precedence(expr);
+        let same_level = match expr {
+            // a + (b - c) is a + b - c, but a - (b + c) isn't a - b + c
+            Expr::Binary { operator, .. } if right => match parent {
+                Token::Plus => false,
+                Token::Star => operator.token == Token::Percent,
+                _ => true,
+            },
+            Expr::Binary { .. } => *parent == Token::Caret,
+            _ => false,
+        };
+        let negative = precedence == 8 && (right || *parent == Token::Caret);
+
+        let text = Self::format_expr(expr);
+        if precedence < parent_precedence || (precedence == parent_precedence && same_level) || negative {
+            format!("({})", text)
+        } else {
+            text
+        }
+    }
+
     fn format_expr(expr: &Expr) -> String {
         match expr {
             Expr::Binary { left, operator, right } => {
-                let l = Self::format_expr(left);
-                let r = Self::format_expr(right);
+                let l = Self::format_operand(left, &operator.token, false);
+                let r = Self::format_operand(right, &operator.token, true);
                 let op = match operator.token {
                     Token::Plus => "+",
                     Token::Minus => "-",
                     Token::Star => "*",
                     Token::Slash => "/",
                     Token::Caret => "^",
+                    Token::Percent => "%",
                     Token::EqualEqual => "==",
                     Token::BangEqual => "!=",
                     Token::Greater => ">",
@@ -83,7 +495,7 @@ impl Value {
                     Token::Or => "||",
                     _ => "?",
                 };
-                format!("({} {} {})", l, op, r)
+                format!("{} {} {}", l, op, r)
             }
             Expr::Literal { value } => match &value.token {
                 Token::Number(n) => n.to_string(),
@@ -92,12 +504,29 @@ impl Value {
                 Token::False => "false".to_string(),
                 Token::Partial => "partial".to_string(),
                 Token::Unknown => "unknown".to_string(),
+                Token::Empty => "empty".to_string(),
                 _ => format!("{:?}", value.token),
             },
             Expr::Variable { name } => {
                 if let Token::Identifier(n) = &name.token { n.clone() } else { format!("{:?}", name.token) }
             }
             Expr::Grouping { expression } => format!("({})", Self::format_expr(expression)),
+            Expr::Block { .. } => "{...}".to_string(),
+            Expr::Unary { operator, right } => {
+                let op = if operator.token == Token::Bang { "!" } else { "-" };
+                // -(a + b) needs them, and so does -(-a), which would read as a decrement
+                if Self::precedence(right) <= Self::precedence(expr) {
+                    format!("{}({})", op, Self::format_expr(right))
+                } else {
+                    format!("{}{}", op, Self::format_expr(right))
+                }
+            }
+            Expr::Call { callee, arguments, .. } => {
+                let arguments: Vec<String> = arguments.iter().map(Self::format_expr).collect();
+                format!("{}({})", Self::format_expr(callee), arguments.join(", "))
+            }
+            Expr::Get { object, name } => format!("{}.{}", Self::format_expr(object), name.token_to_string()),
+            Expr::Index { object, index, .. } => format!("{}[{}]", Self::format_expr(object), Self::format_expr(index)),
             Expr::Postfix { name, operator } => {
                 let n = match &name.token {
                     Token::Identifier(s) => s.as_str(),
@@ -110,10 +539,82 @@ impl Value {
                 };
                 format!("{}{}", n, op)
             }
+            Expr::Interval { min, max, .. } => format!("[{}..{}]", Self::format_expr(min), Self::format_expr(max)),
+            Expr::OneOf { options, .. } => {
+                let options: Vec<String> = options.iter().map(Self::format_expr).collect();
+                format!("{{{}}}", options.join(", "))
+            }
+            Expr::Conditional { condition, then_branch, else_branch } => format!(
+                "if {} {{ {} }} else {{ {} }}",
+                Self::format_expr(condition),
+                Self::format_expr(then_branch),
+                Self::format_expr(else_branch)
+            ),
             _ => "...".to_string(),
         }
     }
 
+    // Dividing by a negative interval can leave -0 behind, and it should read 'inf', not 'infinity'
+    fn format_bound(n: f64) -> String {
+        if n == 0.0 {
+            "0".to_string()
+        } else if n.is_infinite() {
+            if n > 0.0 { "inf".to_string() } else { "-inf".to_string() }
+        } else if n.is_subnormal() {
+            // What's left of a zero after outward rounding, written out it would be hundreds of digits
+            format!("{:e}", n)
+        } else {
+            n.to_string()
+        }
+    }
+
+    // Arithmetic where an affine value is involved, the plain interval result is worked out too
+    // and the affine one is only kept where it is tighter
+    fn affine(
+        &self,
+        other: &Value,
+        plain_op: fn(&Value, &Value) -> Result<Value, Error>,
+        form_op: fn(&Affine, &Affine, &Value) -> Option<Affine>,
+    ) -> Result<Option<Value>, Error> {
+        if !matches!(self, Value::Affine(_)) && !matches!(other, Value::Affine(_)) {
+            return Ok(None);
+        }
+        let form = |v: &Value| match v {
+            Value::Affine(a) => Some(a.clone()),
+            Value::Number(n) => Some(Affine::new(*n, *n)),
+            Value::Interval(min, max) => Some(Affine::new(*min, *max)),
+            _ => None,
+        };
+
+        let plain = plain_op(&self.clone().plain(), &other.clone().plain())?;
+        let (Some(a), Some(b)) = (form(self), form(other)) else {
+            return Ok(Some(plain));
+        };
+        let result = match (form_op(&a, &b, other), &plain) {
+            (Some(result), Value::Interval(min, max)) => result.clamp((*min, *max)),
+            (_, Value::Union(_)) | (None, _) => return Ok(Some(plain)),
+            // plain intervals that look the same are taken to be the same, the symbols know better
+            (Some(result), _) => result,
+        };
+        Ok(Some(match result.range() {
+            (min, max) if min == max => Value::Number(min),
+            _ => Value::Affine(result),
+        }))
+    }
+
+    // Arithmetic with a distribution runs on every sample
+    fn sampled(&self, other: &Value, f: fn(f64, f64) -> f64) -> Result<Option<Value>, Error> {
+        match (self, other) {
+            (Value::Dist(a), Value::Dist(b)) => Ok(Some(Value::Dist(a.zip(b, f)))),
+            (Value::Dist(a), Value::Number(n)) => Ok(Some(Value::Dist(a.map(|x| f(x, *n))))),
+            (Value::Number(n), Value::Dist(b)) => Ok(Some(Value::Dist(b.map(|y| f(*n, y))))),
+            (Value::Dist(_), _) | (_, Value::Dist(_)) => Err(Self::err(
+                "Distributions only work with numbers and other distributions, dist.uniform() turns an interval into one".to_string(),
+            )),
+            _ => Ok(None),
+        }
+    }
+
     fn err(msg: String) -> Error {
         Error::new(
             TokenSpan {
@@ -126,7 +627,16 @@ impl Value {
     }
 
     pub fn add(&self, other: &Value) -> Result<Value, Error> {
-        match (self, other) {
+        if let Some(result) = self.piecewise(other, Value::add)? {
+            return Ok(result);
+        }
+        if let Some(result) = self.sampled(other, |x, y| x + y)? {
+            return Ok(result);
+        }
+        if let Some(result) = self.affine(other, Value::add, |a, b, _| Some(a.add(b)))? {
+            return Ok(result);
+        }
+        let result = match (self, other) {
             (Value::Number(a), Value::Number(b)) => Ok(Value::Number(a + b)),
             (Value::String(s1), Value::String(s2)) => Ok(Value::String(format!("{}{}", s1, s2))),
 
@@ -138,11 +648,21 @@ impl Value {
             },
 
             _ => Err(Self::err("Invalid types for addition".to_string())),
-        }
+        };
+        result.map(Value::outward)
     }
 
     pub fn sub(&self, other: &Value) -> Result<Value, Error> {
-        match (self, other) {
+        if let Some(result) = self.piecewise(other, Value::sub)? {
+            return Ok(result);
+        }
+        if let Some(result) = self.sampled(other, |x, y| x - y)? {
+            return Ok(result);
+        }
+        if let Some(result) = self.affine(other, Value::sub, |a, b, _| Some(a.sub(b)))? {
+            return Ok(result);
+        }
+        let result = match (self, other) {
             (l, r) if l == r && !l.is_symbolic_or_unknown() => Ok(Value::Number(0.0)),
             (Value::Number(a), Value::Number(b)) => Ok(Value::Number(a - b)),
 
@@ -154,11 +674,21 @@ impl Value {
             },
 
             _ => Err(Self::err("Invalid types for subtraction".to_string())),
-        }
+        };
+        result.map(Value::outward)
     }
 
     pub fn mul(&self, other: &Value) -> Result<Value, Error> {
-        match (self, other) {
+        if let Some(result) = self.piecewise(other, Value::mul)? {
+            return Ok(result);
+        }
+        if let Some(result) = self.sampled(other, |x, y| x * y)? {
+            return Ok(result);
+        }
+        if let Some(result) = self.affine(other, Value::mul, |a, b, _| Some(a.mul(b)))? {
+            return Ok(result);
+        }
+        let result = match (self, other) {
             (Value::Number(n), _) | (_, Value::Number(n)) if *n == 0.0 => Ok(Value::Number(0.0)),
             (Value::Number(a), Value::Number(b)) => Ok(Value::Number(a * b)),
 
@@ -177,7 +707,8 @@ impl Value {
             },
 
             _ => Err(Self::err("Invalid types for multiplication".to_string())),
-        }
+        };
+        result.map(Value::outward)
     }
 
     pub fn div(&self, other: &Value) -> Result<Value, Error> {
@@ -185,15 +716,27 @@ impl Value {
             return Ok(Value::Unknown);
         }
 
+        if let Some(result) = self.piecewise(other, Value::div)? {
+            return Ok(result);
+        }
+        if let Some(result) = self.sampled(other, |x, y| x / y)? {
+            return Ok(result);
+        }
+
         if self == other {
             match self {
                 Value::Number(n) if *n != 0.0 => return Ok(Value::Number(1.0)),
                 Value::Interval(min, max) if *min > 0.0 || *max < 0.0 => return Ok(Value::Number(1.0)),
+                Value::Affine(a) if a.range().0 > 0.0 || a.range().1 < 0.0 => return Ok(Value::Number(1.0)),
                 _ => {} 
             }
         }
 
-        match (self, other) {
+        if let Some(result) = self.affine(other, Value::div, |a, b, _| b.recip().map(|r| a.mul(&r)))? {
+            return Ok(result);
+        }
+
+        let result = match (self, other) {
             (Value::Number(a), Value::Number(b)) => {
                 if *b == 0.0 { return Err(Self::err("Division by zero!".to_string())); }
                 Ok(Value::Number(a / b))
@@ -208,7 +751,7 @@ impl Value {
             }
             (Value::Number(a), Value::Interval(b_min, b_max)) => {
                 if *b_min <= 0.0 && *b_max >= 0.0 {
-                    return Err(Self::err("Division by interval containing zero".to_string()));
+                    return Self::extended_div(*a, *a, *b_min, *b_max).map(Value::outward);
                 }
                 let vals = [a / b_min, a / b_max];
                 Ok(Value::Interval(
@@ -218,7 +761,7 @@ impl Value {
             }
             (Value::Interval(a_min, a_max), Value::Interval(b_min, b_max)) => {
                 if *b_min <= 0.0 && *b_max >= 0.0 {
-                    return Err(Self::err("Division by interval containing zero".to_string()));
+                    return Self::extended_div(*a_min, *a_max, *b_min, *b_max).map(Value::outward);
                 }
 
                 let b_recip_min = 1.0 / b_max;
@@ -237,28 +780,230 @@ impl Value {
                 ))
             }
             _ => Err(Self::err("Division not supported for these types".to_string())),
+        };
+        result.map(Value::outward)
+    }
+
+    // [a_min..a_max] / [b_min..b_max] with zero inside the divisor, the result splits around the pole
+    fn extended_div(a_min: f64, a_max: f64, b_min: f64, b_max: f64) -> Result<Value, Error> {
+        if settings::current().strict_division {
+            return Err(Self::err("Division by interval containing zero".to_string()));
         }
+        if b_min == 0.0 && b_max == 0.0 {
+            return Err(Self::err("Division by zero!".to_string()));
+        }
+
+        let (inf, neg_inf) = (f64::INFINITY, f64::NEG_INFINITY);
+        let pieces = if a_min <= 0.0 && a_max >= 0.0 {
+            vec![(neg_inf, inf)]
+        } else if a_min > 0.0 {
+            let mut pieces = Vec::new();
+            if b_min < 0.0 { pieces.push((neg_inf, a_min / b_min)); }
+            if b_max > 0.0 { pieces.push((a_min / b_max, inf)); }
+            pieces
+        } else {
+            let mut pieces = Vec::new();
+            if b_max > 0.0 { pieces.push((neg_inf, a_max / b_max)); }
+            if b_min < 0.0 { pieces.push((a_max / b_min, inf)); }
+            pieces
+        };
+        Ok(Value::from_pieces(pieces))
     }
 
     pub fn pow(&self, other: &Value) -> Result<Value, Error> {
-        match (self, other) {
-            (Value::Number(a), Value::Number(b)) => Ok(Value::Number(a.powf(*b))),
+        if let Some(result) = self.piecewise(other, Value::pow)? {
+            return Ok(result);
+        }
+        if let Some(result) = self.sampled(other, f64::powf)? {
+            return Ok(result);
+        }
+        if let Some(result) = self.affine(other, Value::pow, |a, _, exponent| match exponent {
+            // whole powers are repeated products, so x^2 still knows it is x * x
+            Value::Number(n) if n.fract() == 0.0 && (1.0..=16.0).contains(n) => {
+                Some((1..*n as usize).fold(a.clone(), |product, _| product.mul(a)))
+            }
+            _ => None,
+        })? {
+            return Ok(result);
+        }
+        let result = match (self, other) {
+            (Value::Number(a), Value::Number(b)) => {
+                if *a < 0.0 && b.fract() != 0.0 {
+                    let Some((p, q)) = odd_root(*b) else {
+                        return Err(Self::err("Negative number to a fractional power".to_string()));
+                    };
+                    return Value::Number(-(-a).powf(1.0 / q)).pow(&Value::Number(p));
+                }
+                Ok(Value::Number(a.powf(*b)))
+            }
 
+            (Value::Interval(..), Value::Number(n)) if *n == 0.0 => Ok(Value::Number(1.0)),
+            (Value::Interval(min, max), Value::Number(n)) if n.fract() == 0.0 => {
+                if *n < 0.0 {
+                    // x^-n is 1 / x^n, so zero inside the interval splits the result like a division
+                    let positive = Value::Interval(*min, *max).pow(&Value::Number(-n))?;
+                    return Value::Number(1.0).div(&positive);
+                }
+                let (p1, p2) = (min.powf(*n), max.powf(*n));
+                if n % 2.0 == 0.0 && *min <= 0.0 && *max >= 0.0 {
+                    Ok(Value::Interval(0.0, p1.max(p2)))
+                } else {
+                    Ok(Value::Interval(p1.min(p2), p1.max(p2)))
+                }
+            }
             (Value::Interval(min, max), Value::Number(n)) => {
-                let p1 = min.powf(*n);
-                let p2 = max.powf(*n);
-                let mut low = p1.min(p2);
-                let high = p1.max(p2);
-                if n % 2.0 == 0.0 && *min <= 0.0 && *max >= 0.0 { 
-                    low = 0.0; 
+                if *min < 0.0
+                    && let Some((p, q)) = odd_root(*n)
+                {
+                    // odd roots keep the sign and only go up, what is left is a whole power
+                    let root = |x: f64| x.signum() * x.abs().powf(1.0 / q);
+                    return Value::Interval(root(*min), root(*max)).pow(&Value::Number(p));
                 }
-                Ok(Value::Interval(low, high))
-            },
+                if *max < 0.0 {
+                    return Err(Self::err("Fractional power of an interval with only negative numbers".to_string()));
+                }
+                // the negative part has no fractional power, see power_clips
+                let min = min.max(0.0);
+                let (p1, p2) = (min.powf(*n), max.powf(*n));
+                Ok(Value::Interval(p1.min(p2), p1.max(p2)))
+            }
+
+            (Value::Number(b), Value::Interval(min, max)) => {
+                if *b < 0.0 {
+                    return Err(Self::err("Negative number to an interval power, only whole powers are defined".to_string()));
+                }
+                if *b == 0.0 && *min < 0.0 {
+                    return Err(Self::err("Division by zero!".to_string()));
+                }
+                let (p1, p2) = (b.powf(*min), b.powf(*max));
+                Ok(Value::Interval(p1.min(p2), p1.max(p2)))
+            }
+
+            // x^y only grows or shrinks in each of x and y, so the corners hold the extremes
+            (Value::Interval(b_min, b_max), Value::Interval(e_min, e_max)) => {
+                if *b_max < 0.0 {
+                    return Err(Self::err("Interval power of an interval with only negative numbers".to_string()));
+                }
+                let b_min = &b_min.max(0.0);
+                let corners = [b_min.powf(*e_min), b_min.powf(*e_max), b_max.powf(*e_min), b_max.powf(*e_max)];
+                Ok(Value::Interval(
+                    corners.iter().copied().fold(f64::INFINITY, f64::min),
+                    corners.iter().copied().fold(f64::NEG_INFINITY, f64::max),
+                ))
+            }
             _ => Err(Self::err("Invalid types for exponentiation".to_string())),
+        };
+        result.map(Value::outward)
+    }
+
+    // Whether x^e is only defined for part of x, [-1..4]^0.5 leaves the negative numbers out
+    pub fn power_clips(&self, exponent: &Value) -> bool {
+        let Some(Value::Interval(min, max)) = self.hull() else {
+            return false;
+        };
+        let fractional = match exponent.hull() {
+            Some(Value::Number(n)) => n.fract() != 0.0 && odd_root(n).is_none(),
+            Some(Value::Interval(..)) => true,
+            _ => false,
+        };
+        fractional && min < 0.0 && max >= 0.0
+    }
+
+    pub fn rem(&self, other: &Value) -> Result<Value, Error> {
+        if let Some(result) = self.sampled(other, f64::rem_euclid)? {
+            return Ok(result);
+        }
+        if let Value::Affine(_) = self {
+            return self.clone().plain().rem(other);
+        }
+        match (self, other) {
+            (_, Value::Number(m)) if *m == 0.0 => Err(Self::err("Modulo by zero!".to_string())),
+            (Value::Number(a), Value::Number(m)) => Ok(Value::Number(a.rem_euclid(*m))),
+
+            (Value::Bounded(b), Value::Number(m)) if b.modulus.is_some_and(|(bm, _)| bm % m == 0.0) => {
+                let (_, r) = b.modulus.unwrap();
+                Ok(Value::Number(r.rem_euclid(*m)))
+            }
+            (Value::Interval(min, max), Value::Number(m)) if *min >= 0.0 && *max < m.abs() => {
+                Ok(Value::Interval(*min, *max))
+            }
+            (Value::Interval(..) | Value::Bounded(_), Value::Number(m)) => Ok(Value::Interval(0.0, m.abs())),
+
+            (Value::Number(_) | Value::Interval(..) | Value::Bounded(_), Value::Interval(m_min, m_max)) => {
+                if *m_min <= 0.0 && *m_max >= 0.0 {
+                    return Err(Self::err("Modulo by an interval containing zero".to_string()));
+                }
+                let (smallest, reach) = (m_min.abs().min(m_max.abs()), m_min.abs().max(m_max.abs()));
+                // a remainder of something that isn't negative is never bigger than it
+                let (min, max) = match self {
+                    Value::Number(a) => (*a, *a),
+                    Value::Interval(min, max) => (*min, *max),
+                    _ => (f64::NEG_INFINITY, f64::INFINITY),
+                };
+                if min >= 0.0 && max < smallest {
+                    return Ok(if min == max { Value::Number(min) } else { Value::Interval(min, max) });
+                }
+                if min >= 0.0 {
+                    return Ok(Value::Interval(0.0, max.min(reach)));
+                }
+                Ok(Value::Interval(0.0, reach))
+            }
+
+            _ => Err(Self::err("Invalid types for modulo".to_string())),
+        }
+    }
+
+    // Compares every option on one side, the answer is only certain when they all agree
+    fn compare_each(options: Vec<Value>, other: &Value, op: &Token, swapped: bool) -> Result<Value, Error> {
+        let mut result: Option<SKBool> = None;
+        for option in options {
+            let compared = if swapped { other.compare(&option, op)? } else { option.compare(other, op)? };
+            let Value::Bool(b) = compared else { return Ok(Value::Bool(SKBool::Partial)) };
+            result = Some(match result {
+                Some(acc) => logic::either(acc, b),
+                None => b,
+            });
         }
+        Ok(Value::Bool(result.unwrap_or(SKBool::Partial)))
     }
 
     pub fn compare(&self, other: &Value, op: &Token) -> Result<Value, Error> {
+        let op_str = match op {
+            Token::EqualEqual => "==",
+            Token::BangEqual => "!=",
+            Token::Greater => ">",
+            Token::GreaterEqual => ">=",
+            Token::Less => "<",
+            Token::LessEqual => "<=",
+            _ => "",
+        };
+
+        if let (Value::Affine(_), _) | (_, Value::Affine(_)) = (self, other) {
+            return self.clone().plain().compare(&other.clone().plain(), op);
+        }
+
+        match (self, other) {
+            (Value::Dist(a), Value::Dist(b)) => return Ok(Value::Bool(a.chance(|i| b.sample(i), op_str))),
+            (Value::Dist(a), Value::Number(n)) => return Ok(Value::Bool(a.chance(|_| *n, op_str))),
+            (Value::Number(n), Value::Dist(b)) => return Ok(Value::Bool(b.chance(|_| *n, logic::flip(op_str)))),
+            (Value::OneOf(options), other) | (other, Value::OneOf(options)) => {
+                let swapped = !matches!(self, Value::OneOf(_));
+                return Self::compare_each(options.clone(), other, op, swapped);
+            }
+            // nothing to compare, so nothing is equal or ordered, != is still the opposite of ==
+            (Value::Empty, _) | (_, Value::Empty) => {
+                return Ok(Value::Bool(if *op == Token::BangEqual { SKBool::True } else { SKBool::False }));
+            }
+            (Value::Union(pieces), other) | (other, Value::Union(pieces)) => {
+                let swapped = !matches!(self, Value::Union(_));
+                let pieces = pieces.iter().map(|(min, max)| Value::Interval(*min, *max)).collect();
+                return Self::compare_each(pieces, other, op, swapped);
+            }
+            (Value::Bounded(bounds), other) => return Ok(Value::Bool(bounds.compare(other, op_str))),
+            (other, Value::Bounded(bounds)) => return Ok(Value::Bool(bounds.compare(other, logic::flip(op_str)))),
+            _ => {}
+        }
+
         match (self, other) {
             (Value::Number(a), Value::Number(b)) => {
                 let op_str = match op {
@@ -320,13 +1065,51 @@ impl fmt::Display for Value {
             Value::Bool(SKBool::True) => write!(f, "true"),
             Value::Bool(SKBool::False) => write!(f, "false"),
             Value::Bool(SKBool::Partial) => write!(f, "partial"),
-            Value::Interval(min, max) => write!(f, "[{}..{}]", min, max),
+            Value::Bool(SKBool::Weighted(..)) => write!(f, "partial"), // dist.probability() gives the estimate
+            Value::Interval(min, max) => write!(f, "[{}..{}]", Self::format_bound(*min), Self::format_bound(*max)),
+            Value::Affine(a) => write!(f, "{}", Value::Interval(a.range().0, a.range().1)),
+            Value::Dist(d) => write!(f, "{}", d),
+            Value::Union(pieces) => {
+                let pieces: Vec<String> = pieces
+                    .iter()
+                    .map(|(min, max)| format!("[{}..{}]", Self::format_bound(*min), Self::format_bound(*max)))
+                    .collect();
+                write!(f, "{}", pieces.join(" ∪ "))
+            }
+            Value::Empty => write!(f, "empty"),
             Value::Symbolic { expression, .. } => write!(f, "{}", Self::format_expr(expression)),
             Value::Unknown => write!(f, "unknown"),
+            Value::Bounded(b) => write!(f, "unknown {}", Self::format_expr(&b.constraint)),
+            Value::OneOf(options) => {
+                let options: Vec<String> = options.iter().map(|v| v.to_string()).collect();
+                write!(f, "{{{}}}", options.join(", "))
+            }
+            Value::List(items) => {
+                let items: Vec<String> = items.borrow().iter().map(|v| v.to_string()).collect();
+                write!(f, "[{}]", items.join(", "))
+            }
+            Value::Map(entries) => {
+                let entries: Vec<String> = entries.borrow().iter().map(|(k, v)| format!("{}: {}", k, v)).collect();
+                write!(f, "{{{}}}", entries.join(", "))
+            }
             Value::NativeFn(_) => write!(f, "<native fn>"),
             Value::Function(_) => write!(f, "<function>"),
             Value::Module(_) => write!(f, "<module>"),
+            Value::Struct(def) => write!(f, "<struct {}>", def.name),
+            Value::Instance(instance) => {
+                let instance = instance.borrow();
+                let fields: Vec<String> = instance.fields.iter().map(|(k, v)| format!("{}: {}", k, v)).collect();
+                write!(f, "{} {{ {} }}", instance.def.name, fields.join(", "))
+            }
             Value::None => write!(f, "none"),
         }
     }
-}
\ No newline at end of file
+}
+
+// An exponent p / q with an odd q, which negative numbers can still be raised to: (-8)^(1/3) is -2
+fn odd_root(exponent: f64) -> Option<(f64, f64)> {
+    (1..100).step_by(2).map(f64::from).find_map(|q| {
+        let p = exponent * q;
+        ((p - p.round()).abs() < 1e-9).then_some((p.round(), q))
+    })
+}
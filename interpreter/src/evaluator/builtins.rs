@@ -1,12 +1,15 @@
-use crate::core::value::{Value, SKBool};
+use crate::core::value::{Bounds, NativeFn, SKBool, Value};
+use crate::core::algebra;
+use crate::parser::ast::Expr;
 use crate::evaluator::eval::Evaluator;
+use crate::evaluator::trace::Trace;
 use crate::core::error::Error;
-use crate::parser::lexer::TokenSpan;
+use crate::parser::lexer::{Token, TokenSpan};
 use std::io::{self, Write};
 
-pub fn print(args: Vec<Value>, _span: TokenSpan, _: &mut Evaluator) -> Result<Value, Error> {
+pub fn print(args: Vec<Value>, _span: TokenSpan, eval: &mut Evaluator) -> Result<Value, Error> {
     for arg in args {
-        print!("{} ", arg);
+        print!("{} ", eval.reveal(arg)?);
     }
     println!();
     Ok(Value::None)
@@ -17,12 +20,219 @@ pub fn resolve(args: Vec<Value>, span: TokenSpan, eval: &mut Evaluator) -> Resul
         Some(Value::Symbolic { expression, .. }) => {
             eval.evaluate_expression(*expression.clone())
         }
-        Some(Value::Unknown) => Ok(Value::None),
+        Some(Value::Unknown) | Some(Value::Bounded(_)) => Ok(Value::None),
         Some(val) => Ok(val.clone()),
         None => Err(eval.error(span, "resolve() expects 1 argument")),
     }
 }
 
+// subst(f, {x: 3}) swaps variables of a formula for values or other formulas
+pub fn subst(args: Vec<Value>, span: TokenSpan, eval: &mut Evaluator) -> Result<Value, Error> {
+    match (args.first(), args.get(1)) {
+        (Some(Value::Symbolic { expression, is_quiet }), Some(Value::Map(entries))) => {
+            let replacements: Vec<(String, Expr)> = entries
+                .borrow()
+                .iter()
+                .map(|(name, value)| (name.clone(), eval.value_to_expr(value.clone())))
+                .collect();
+            let replace = |name: &str| replacements.iter().find(|(n, _)| n == name).map(|(_, e)| e.clone());
+            let expression = algebra::simplify(&algebra::substitute(expression, &replace));
+            Ok(Value::Symbolic { expression: Box::new(expression), is_quiet: *is_quiet })
+        }
+        (Some(value), Some(Value::Map(_))) => Ok(value.clone()),
+        _ => Err(eval.error(span, "subst() expects a formula and a map of variables to swap")),
+    }
+}
+
+// Resolves what can already be resolved, leaving the unknown parts of the formula symbolic
+pub fn partial(args: Vec<Value>, span: TokenSpan, eval: &mut Evaluator) -> Result<Value, Error> {
+    match args.first() {
+        Some(Value::Symbolic { expression, is_quiet }) => {
+            let expression = algebra::simplify(&eval.partial(expression, &mut Vec::new()));
+            Ok(Value::Symbolic { expression: Box::new(expression), is_quiet: *is_quiet })
+        }
+        Some(value) => Ok(value.clone()),
+        None => Err(eval.error(span, "partial() expects 1 argument")),
+    }
+}
+
+// A variable given by name, or as a symbolic that is only that variable
+fn variable_name(arg: Option<&Value>) -> Option<String> {
+    match arg {
+        Some(Value::String(name)) => Some(name.clone()),
+        Some(Value::Symbolic { expression, .. }) if let Expr::Variable { name } = expression.as_ref() => Some(name.token_to_string()),
+        _ => None,
+    }
+}
+
+// derive(f, "x") is the symbolic derivative of f with respect to x
+pub fn derive(args: Vec<Value>, span: TokenSpan, eval: &mut Evaluator) -> Result<Value, Error> {
+    let Some(var) = variable_name(args.get(1)) else {
+        return Err(eval.error(span, "derive() expects a formula and the name of a variable"));
+    };
+
+    match args.first() {
+        Some(Value::Symbolic { expression, is_quiet }) => {
+            // a derivative can need functions the formula didn't call, like math.ln for x ^ x
+            let library = match eval.env.borrow().get("math") {
+                Ok(Value::Module(_)) => Some(Expr::Variable { name: TokenSpan { token: Token::Identifier("math".to_string()), ..span.clone() } }),
+                _ => None,
+            };
+            let inline = |name: &str| eval.symbolic_formula(name);
+            match algebra::derive(expression, &var, &inline, library) {
+                Ok(derivative) => Ok(Value::Symbolic { expression: Box::new(derivative), is_quiet: *is_quiet }),
+                Err(msg) => Err(eval.error(span, msg)),
+            }
+        }
+        Some(Value::Number(_) | Value::Interval(..)) => Ok(Value::Number(0.0)),
+        _ => Err(eval.error(span, "derive() expects a formula and the name of a variable")),
+    }
+}
+
+// Native functions whose first argument is an equation, they see it as a formula instead of its value
+pub fn takes_equation(func: NativeFn) -> bool {
+    std::ptr::fn_addr_eq(func, solve as NativeFn)
+}
+
+// solve(lhs == rhs, "x") finds the values of x that make the equation hold, and gives them to x if it was unknown
+pub fn solve(args: Vec<Value>, span: TokenSpan, eval: &mut Evaluator) -> Result<Value, Error> {
+    let Some(var) = variable_name(args.get(1)) else {
+        return Err(eval.error(span, "solve() expects an equation and the name of a variable"));
+    };
+    let equation = match args.first() {
+        Some(Value::Symbolic { expression, .. }) => Some(*expression.clone()),
+        _ => None,
+    };
+    let Some(Expr::Binary { left, operator, right }) = equation.filter(|e| matches!(e, Expr::Binary { operator, .. } if operator.token == Token::EqualEqual)) else {
+        return Err(eval.error(span, "solve() expects an equation like lhs == rhs"));
+    };
+
+    let difference = Expr::Binary { left, operator: TokenSpan { token: Token::Minus, ..operator }, right };
+    let inline = |name: &str| if name == var { None } else { eval.symbolic_formula(name) };
+    let Some(coefficients) = algebra::polynomial(&difference, &var, &inline) else {
+        return Err(eval.error(span, format!("solve() only works when {} appears in a polynomial", var)));
+    };
+
+    let mut values = Vec::new();
+    for coefficient in coefficients {
+        values.push(eval.evaluate_expression(coefficient)?);
+    }
+    let solution = roots(values).map_err(|msg| eval.error(span.clone(), msg))?;
+
+    let current = eval.env.borrow().get(&var);
+    match current {
+        Err(_) => eval.env.borrow_mut().define(var, solution.clone()),
+        Ok(Value::Unknown) => eval.env.borrow_mut().assign(&var, solution.clone()).map_err(|msg| eval.error(span, msg))?,
+        // only the roots the constraint allows, x > 0 keeps 2 out of x^2 == 4
+        Ok(Value::Bounded(bounds)) => {
+            let allowed = within(solution, &bounds);
+            if matches!(allowed, Value::Empty | Value::Unknown) {
+                return Ok(allowed);
+            }
+            eval.env.borrow_mut().assign(&var, allowed.clone()).map_err(|msg| eval.error(span, msg))?;
+            return Ok(allowed);
+        }
+        Ok(_) => {}
+    }
+    Ok(solution)
+}
+
+// The part of a solution that a constrained unknown can take
+fn within(solution: Value, bounds: &Bounds) -> Value {
+    match solution {
+        Value::Number(n) if bounds.allows(n) => Value::Number(n),
+        Value::Number(_) => Value::Empty,
+        Value::OneOf(options) => {
+            let kept: Vec<Value> = options.into_iter().map(|o| within(o, bounds)).filter(|o| *o != Value::Empty).collect();
+            if kept.is_empty() { Value::Empty } else { Value::one_of(kept) }
+        }
+        Value::Interval(min, max) => {
+            let (min, max) = (min.max(bounds.min.0), max.min(bounds.max.0));
+            if min > max { Value::Empty } else { Value::Interval(min, max) }
+        }
+        other => other,
+    }
+}
+
+// Where c0 + c1 * x + c2 * x^2 + ... is zero
+fn roots(mut coefficients: Vec<Value>) -> Result<Value, String> {
+    while let Some(Value::Number(c)) = coefficients.last() && *c == 0.0 {
+        coefficients.pop();
+    }
+    let numbers: Option<Vec<f64>> = coefficients.iter().map(|c| if let Value::Number(n) = c { Some(*n) } else { None }).collect();
+
+    let mut found = match (coefficients.as_slice(), numbers.as_deref()) {
+        ([], _) => return Ok(Value::Unknown), // holds for any value
+        ([Value::Number(_)], _) => return Ok(Value::Empty),
+        ([_], _) => return Ok(Value::Unknown),
+        ([c0, c1], _) => return Value::Number(0.0).sub(c0).and_then(|c| c.div(c1)).map_err(|e| e.message),
+        (_, Some([c, b, a])) => {
+            let discriminant = b * b - 4.0 * a * c;
+            if discriminant < 0.0 {
+                Vec::new()
+            } else {
+                // Avoids subtracting two close numbers, which would lose the smaller root
+                let q = -0.5 * (b + b.signum() * discriminant.sqrt());
+                if q == 0.0 { vec![0.0] } else { vec![q / a, c / q] }
+            }
+        }
+        (_, Some([c, middle @ .., a])) if middle.iter().all(|m| *m == 0.0) => {
+            let n = middle.len() + 1;
+            let power = -c / a;
+            let root = power.abs().powf(1.0 / n as f64);
+            match (n % 2 == 0, power) {
+                (false, _) => vec![root.copysign(power)],
+                (true, p) if p >= 0.0 => vec![-root, root],
+                _ => Vec::new(),
+            }
+        }
+        (_, Some(_)) => return Err("solve() only solves polynomials up to degree 2, or x^n = c".to_string()),
+        _ => return Err("solve() needs known numbers to solve a polynomial of degree 2 or more".to_string()),
+    };
+
+    found.sort_by(f64::total_cmp);
+    found.dedup();
+    Ok(match found.as_slice() {
+        [] => Value::Empty,
+        [root] => Value::Number(*root),
+        _ => Value::one_of(found.into_iter().map(Value::Number).collect()),
+    })
+}
+
+pub fn explain(args: Vec<Value>, span: TokenSpan, eval: &mut Evaluator) -> Result<Value, Error> {
+    let Some(value) = args.first() else {
+        return Err(eval.error(span, "explain() expects 1 argument"));
+    };
+
+    println!("{}", explanation(value, &eval.arg_trace(0), eval));
+    Ok(Value::None)
+}
+
+// The text explain() prints, the trace is where the value came from
+pub fn explanation(value: &Value, trace: &Trace, eval: &Evaluator) -> String {
+    match value {
+        Value::Symbolic { expression, .. } => {
+            let dependencies = eval.symbolic_dependencies(expression);
+            if dependencies.is_empty() {
+                format!("{} depends on nothing uncertain, resolve() gives its value", value)
+            } else {
+                format!("{} still depends on: {}", value, dependencies.join(", "))
+            }
+        }
+        Value::Number(_) | Value::String(_) | Value::Bool(SKBool::True) | Value::Bool(SKBool::False) => {
+            format!("{} is known", value)
+        }
+        _ if trace.is_empty() => format!("{} is uncertain, but no operation recorded why", value),
+        _ => {
+            let mut text = format!("{} is uncertain because:", value);
+            for line in trace.lines() {
+                text.push_str(&format!("\n  {}", line));
+            }
+            text
+        }
+    }
+}
+
 pub fn input(args: Vec<Value>, span: TokenSpan, eval: &mut Evaluator) -> Result<Value, Error> {
     if let Some(msg) = args.first() {
         print!("{}", msg);
@@ -73,7 +283,7 @@ pub fn impossible(args: Vec<Value>, span: TokenSpan, eval: &mut Evaluator) -> Re
 
 pub fn possible(args: Vec<Value>, _span: TokenSpan, _: &mut Evaluator) -> Result<Value, Error> {
     match args.first() {
-        Some(Value::Bool(SKBool::True)) | Some(Value::Bool(SKBool::Partial)) => Ok(Value::Bool(SKBool::True)),
+        Some(Value::Bool(SKBool::True | SKBool::Partial | SKBool::Weighted(..))) => Ok(Value::Bool(SKBool::True)),
         Some(Value::Bool(SKBool::False)) => Ok(Value::Bool(SKBool::False)),
         _ => Ok(Value::Bool(SKBool::True)), 
     }
@@ -81,7 +291,9 @@ pub fn possible(args: Vec<Value>, _span: TokenSpan, _: &mut Evaluator) -> Result
 
 pub fn known(args: Vec<Value>, span: TokenSpan, eval: &mut Evaluator) -> Result<Value, Error> {
     match args.first() {
-        Some(Value::Unknown) | Some(Value::Symbolic { .. }) => Ok(Value::Bool(SKBool::False)),
+        Some(Value::Unknown) | Some(Value::Bounded(_)) | Some(Value::OneOf(_)) | Some(Value::Symbolic { .. }) => {
+            Ok(Value::Bool(SKBool::False))
+        }
         Some(_) => Ok(Value::Bool(SKBool::True)),
         None => Err(eval.error(span, "known() expects 1 argument")),
     }
@@ -92,13 +304,20 @@ pub fn kind(args: Vec<Value>, _span: TokenSpan, _: &mut Evaluator) -> Result<Val
         Some(Value::Number(_)) => "number",
         Some(Value::String(_)) => "string",
         Some(Value::Bool(_)) => "bool",
-        Some(Value::Interval(..)) => "interval",
-        Some(Value::Unknown) => "unknown",
+        Some(Value::Interval(..)) | Some(Value::Affine(_)) | Some(Value::Union(_)) => "interval",
+        Some(Value::Empty) => "empty",
+        Some(Value::Unknown) | Some(Value::Bounded(_)) => "unknown",
+        Some(Value::OneOf(_)) => "one of",
+        Some(Value::Dist(_)) => "distribution",
         Some(Value::Symbolic { is_quiet: true, .. }) => "quiet",
         Some(Value::Symbolic { .. }) => "symbolic",
+        Some(Value::List(_)) => "list",
+        Some(Value::Map(_)) => "map",
         Some(Value::NativeFn(_)) => "native function",
         Some(Value::Function(_)) => "function",
         Some(Value::Module(_)) => "module",
+        Some(Value::Struct(_)) => "struct",
+        Some(Value::Instance(instance)) => return Ok(Value::String(instance.borrow().def.name.clone())),
         Some(Value::None) | None => "none",
     };
     Ok(Value::String(t.to_string()))
@@ -108,13 +327,9 @@ pub fn intersect(args: Vec<Value>, span: TokenSpan, eval: &mut Evaluator) -> Res
     if args.len() != 2 {
         return Err(eval.error(span, "intersect() expects 2 arguments"));
     }
-    match (&args[0], &args[1]) {
-        (Value::Interval(min1, max1), Value::Interval(min2, max2)) => {
-            let low = min1.max(*min2);
-            let high = max1.min(*max2);
-            if low <= high { Ok(Value::Interval(low, high)) } else { Ok(Value::None) }
-        }
-        _ => Err(eval.error(span, "intersect() requires two intervals")),
+    match args[0].intersection(&args[1]) {
+        Some(result) => Ok(result),
+        None => Err(eval.error(span, "intersect() requires two intervals")),
     }
 }
 
@@ -122,15 +337,9 @@ pub fn union(args: Vec<Value>, span: TokenSpan, eval: &mut Evaluator) -> Result<
     if args.len() != 2 {
         return Err(eval.error(span, "union() expects 2 arguments"));
     }
-    match (&args[0], &args[1]) {
-        (Value::Interval(min1, max1), Value::Interval(min2, max2)) => {
-            Ok(Value::Interval(min1.min(*min2), max1.max(*max2)))
-        }
-        (Value::Number(n), Value::Interval(min, max)) | (Value::Interval(min, max), Value::Number(n)) => {
-            Ok(Value::Interval(min.min(*n), max.max(*n)))
-        }
-        (Value::Number(n1), Value::Number(n2)) => Ok(Value::Interval(n1.min(*n2), n1.max(*n2))),
-        _ => Err(eval.error(span, "union() expects intervals or numbers")),
+    match args[0].union(&args[1]) {
+        Some(result) => Ok(result),
+        None => Err(eval.error(span, "union() expects intervals or numbers")),
     }
 }
 
@@ -146,4 +355,68 @@ pub fn width(args: Vec<Value>, span: TokenSpan, eval: &mut Evaluator) -> Result<
         Some(Value::Interval(min, max)) => Ok(Value::Number(max - min)),
         _ => Err(eval.error(span, "width() expects an interval")),
     }
-}
\ No newline at end of file
+}
+
+// List Ops
+pub fn len(args: Vec<Value>, span: TokenSpan, eval: &mut Evaluator) -> Result<Value, Error> {
+    match args.first() {
+        Some(Value::List(items)) => Ok(Value::Number(items.borrow().len() as f64)),
+        Some(Value::Map(entries)) => Ok(Value::Number(entries.borrow().len() as f64)),
+        Some(Value::String(s)) => Ok(Value::Number(s.chars().count() as f64)),
+        _ => Err(eval.error(span, "len() expects a list, map or string")),
+    }
+}
+
+pub fn push(args: Vec<Value>, span: TokenSpan, eval: &mut Evaluator) -> Result<Value, Error> {
+    match (args.first(), args.get(1)) {
+        (Some(Value::List(items)), Some(value)) => {
+            let value = value.stored_in(&args[0]);
+            items.borrow_mut().push(value);
+            Ok(Value::None)
+        }
+        _ => Err(eval.error(span, "push() expects a list and a value")),
+    }
+}
+
+pub fn pop(args: Vec<Value>, span: TokenSpan, eval: &mut Evaluator) -> Result<Value, Error> {
+    match args.first() {
+        Some(Value::List(items)) => {
+            let popped = items.borrow_mut().pop();
+            popped.ok_or_else(|| eval.error(span, "pop() called on an empty list"))
+        }
+        _ => Err(eval.error(span, "pop() expects a list")),
+    }
+}
+
+pub fn map(args: Vec<Value>, span: TokenSpan, eval: &mut Evaluator) -> Result<Value, Error> {
+    match (args.first(), args.get(1)) {
+        (Some(Value::List(items)), Some(func)) => {
+            let items = items.borrow().clone();
+            let mut mapped = Vec::with_capacity(items.len());
+            for item in items {
+                mapped.push(eval.call_value(func.clone(), vec![item], span.clone())?);
+            }
+            Ok(Value::list(mapped))
+        }
+        _ => Err(eval.error(span, "map() expects a list and a function")),
+    }
+}
+
+// Only elements that certainly pass the predicate are kept, just like a strict if
+pub fn filter(args: Vec<Value>, span: TokenSpan, eval: &mut Evaluator) -> Result<Value, Error> {
+    match (args.first(), args.get(1)) {
+        (Some(Value::List(items)), Some(func)) => {
+            let items = items.borrow().clone();
+            let mut kept = Vec::new();
+            for item in items {
+                match eval.call_value(func.clone(), vec![item.clone()], span.clone())? {
+                    Value::Bool(SKBool::True) => kept.push(item),
+                    Value::Bool(_) => {}
+                    _ => return Err(eval.error(span, "filter() predicate must return a boolean")),
+                }
+            }
+            Ok(Value::list(kept))
+        }
+        _ => Err(eval.error(span, "filter() expects a list and a function")),
+    }
+}
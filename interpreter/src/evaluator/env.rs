@@ -1,23 +1,34 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::rc::Rc;
 use std::cell::RefCell;
 use crate::core::value::Value;
 use crate::evaluator::builtins;
+use crate::evaluator::trace::Trace;
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct Environment {
     values: HashMap<String, Value>,
+    constants: HashSet<String>,
+    traces: HashMap<String, Trace>,
     pub enclosing: Option<Rc<RefCell<Environment>>>,
 }
 
+impl Default for Environment {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Environment {
     pub fn new() -> Self {
         let mut env = Self {
             values: HashMap::new(),
+            constants: HashSet::new(),
+            traces: HashMap::new(),
             enclosing: None,
         };
 
-        let defs: [(&str, crate::core::value::NativeFn); 14] = [
+        let defs: [(&str, crate::core::value::NativeFn); 24] = [
             ("print", builtins::print),
             ("input", builtins::input),
             ("num", builtins::num),
@@ -32,6 +43,16 @@ impl Environment {
             ("union", builtins::union),
             ("mid", builtins::mid),
             ("width", builtins::width),
+            ("len", builtins::len),
+            ("push", builtins::push),
+            ("pop", builtins::pop),
+            ("map", builtins::map),
+            ("filter", builtins::filter),
+            ("explain", builtins::explain),
+            ("derive", builtins::derive),
+            ("solve", builtins::solve),
+            ("subst", builtins::subst),
+            ("partial", builtins::partial),
         ];
 
         for (name, func) in defs {
@@ -44,14 +65,28 @@ impl Environment {
     pub fn new_enclosed(enclosing: Rc<RefCell<Environment>>) -> Self {
         Self {
             values: HashMap::new(),
+            constants: HashSet::new(),
+            traces: HashMap::new(),
             enclosing: Some(enclosing),
         }
     }
 
     pub fn define(&mut self, name: String, value: Value) {
+        self.traces.remove(&name);
         self.values.insert(name, value);
     }
 
+    pub fn define_const(&mut self, name: String, value: Value) {
+        self.constants.insert(name.clone());
+        self.traces.remove(&name);
+        self.values.insert(name, value);
+    }
+
+    // Only looks at this scope, inner scopes are free to shadow a constant
+    pub fn is_const(&self, name: &str) -> bool {
+        self.constants.contains(name)
+    }
+
     pub fn get(&self, name: &str) -> Result<Value, String> {
         if let Some(value) = self.values.get(name) {
             return Ok(value.clone());
@@ -66,6 +101,9 @@ impl Environment {
 
     pub fn assign(&mut self, name: &str, value: Value) -> Result<(), String> {
         if self.values.contains_key(name) {
+            if self.constants.contains(name) {
+                return Err(format!("Cannot assign to constant '{}'", name));
+            }
             self.values.insert(name.to_string(), value);
             return Ok(());
         }
@@ -76,4 +114,42 @@ impl Environment {
 
         Err(format!("Undefined variable '{}'", name))
     }
-}
\ No newline at end of file
+
+    // Whether name is a constant in the scope it lives in
+    pub fn resolves_to_const(&self, name: &str) -> bool {
+        if self.values.contains_key(name) {
+            return self.constants.contains(name);
+        }
+        self.enclosing.as_ref().is_some_and(|enclosing| enclosing.borrow().resolves_to_const(name))
+    }
+
+    // Overwrites a variable where it lives, constants included, used to narrow it while an if branch runs
+    // A constant is always put back afterwards, only variables keep what a merged branch left them at
+    pub fn narrow(&mut self, name: &str, value: Value) {
+        if self.values.contains_key(name) {
+            self.values.insert(name.to_string(), value);
+        } else if let Some(ref enclosing) = self.enclosing {
+            enclosing.borrow_mut().narrow(name, value);
+        }
+    }
+
+    // Traces live next to their variable, in whatever scope defined it
+    pub fn set_trace(&mut self, name: &str, trace: Trace) {
+        if self.values.contains_key(name) {
+            self.traces.insert(name.to_string(), trace);
+        } else if let Some(ref enclosing) = self.enclosing {
+            enclosing.borrow_mut().set_trace(name, trace);
+        }
+    }
+
+    pub fn trace(&self, name: &str) -> Trace {
+        if self.values.contains_key(name) {
+            return self.traces.get(name).cloned().unwrap_or_default();
+        }
+
+        match self.enclosing {
+            Some(ref enclosing) => enclosing.borrow().trace(name),
+            None => Trace::new(),
+        }
+    }
+}
@@ -1,21 +1,45 @@
 use crate::parser::ast::{Expr, IfPolicy, Stmt};
 use crate::parser::lexer::{Token, TokenSpan};
-use crate::core::value::{Function, SKBool, Value};
+use crate::core::value::{Bounds, Function, Instance, SKBool, StructDef, Value};
+use std::collections::HashMap;
 use crate::core::logic;
+use crate::core::affine::Affine;
+use crate::core::algebra;
+use crate::core::settings;
 use crate::core::error::{Error, ErrorReporter};
+use crate::evaluator::builtins;
 use crate::evaluator::env::Environment;
+use crate::evaluator::trace::Trace;
 use std::rc::Rc;
 use std::cell::RefCell;
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum ControlFlow {
-    None, Break, Continue,
+    None, Break, Continue, Return(Value),
+}
+
+// Most combinations a "one of" operation works out before falling back to an interval
+const ONE_OF_LIMIT: usize = 64;
+
+// Most iterations a while loop runs once a merge has made it uncertain
+const MERGE_LOOP_LIMIT: usize = 10_000;
+
+// What an uncertain 'x > 5' tells each branch about x
+struct Narrowing {
+    name: String,
+    then_value: Value,
+    else_value: Value,
 }
 
 pub struct Evaluator {
     pub env: Rc<RefCell<Environment>>,
     control_flow: ControlFlow,
+    pending_returns: Vec<(Expr, Value)>, // returns from one side of a merged if, and the condition that took them
     reporter: Rc<RefCell<ErrorReporter>>,
+    trace: Trace, // provenance of the last evaluated expression
+    arg_traces: Vec<Trace>,
+    method_owner: Option<Rc<StructDef>>, // the struct whose method is running
+    callee: Option<Expr>, // how the native function being called was written, e.g. math.sin
 }
 
 impl Evaluator {
@@ -23,7 +47,12 @@ impl Evaluator {
         Self { 
             env,
             control_flow: ControlFlow::None,
+            pending_returns: Vec::new(),
             reporter,
+            trace: Trace::new(),
+            arg_traces: Vec::new(),
+            method_owner: None,
+            callee: None,
         }
     }
 
@@ -32,13 +61,47 @@ impl Evaluator {
         for stmt in statements {
             last_value = self.eval_stmt(stmt)?;
         }
-        Ok(last_value)
+        self.reveal(last_value)
     }
 
     pub fn evaluate_expression(&mut self, expr: Expr) -> Result<Value, Error> {
         self.eval_expr(expr)
     }
 
+    // Quiet symbolics never show their formula, only their current value
+    pub fn reveal(&mut self, value: Value) -> Result<Value, Error> {
+        match value {
+            Value::Symbolic { expression, is_quiet: true } => self.eval_expr(*expression),
+            other => Ok(other),
+        }
+    }
+
+    // Provenance of the n-th argument of the native function being called
+    pub fn arg_trace(&self, n: usize) -> Trace {
+        self.arg_traces.get(n).cloned().unwrap_or_default()
+    }
+
+    // A native function that can't work on a symbolic argument hands back the call itself,
+    // so it is done once the argument can be resolved
+    pub fn symbolic_call(&self, name: &str, paren: TokenSpan, args: Vec<Value>) -> Value {
+        let is_quiet = args.iter().any(|arg| matches!(arg, Value::Symbolic { is_quiet: true, .. }));
+        // called without a written callee, e.g. through map(), 'math.sin' is rebuilt from its name
+        let callee = self.callee.clone().unwrap_or_else(|| {
+            let span = |part: &str| TokenSpan { token: Token::Identifier(part.to_string()), line: paren.line, column: paren.column };
+            let mut parts = name.split('.');
+            let first = Expr::Variable { name: span(parts.next().unwrap_or(name)) };
+            parts.fold(first, |object, part| Expr::Get { object: Box::new(object), name: span(part) })
+        });
+        Value::Symbolic {
+            expression: Box::new(Expr::Call {
+                callee: Box::new(callee),
+                paren,
+                arguments: args.into_iter().map(|arg| self.value_to_expr(arg)).collect(),
+            }),
+            is_quiet,
+        }
+    }
+
     pub fn error(&self, token: TokenSpan, msg: impl Into<String>) -> Error {
         self.reporter.borrow_mut().error(token, msg)
     }
@@ -47,6 +110,12 @@ impl Evaluator {
         self.reporter.borrow_mut().warn(token, msg);
     }
 
+    // Records why the value being evaluated is uncertain, for explain()
+    fn note(&mut self, at: &TokenSpan, message: impl Into<String>) {
+        let file = self.reporter.borrow().source_name();
+        self.trace.note(file, at, message);
+    }
+
     fn execute_block(&mut self, statements: Vec<Stmt>, env: Environment) -> Result<Value, Error> {
         let previous = self.env.clone();
         self.env = Rc::new(RefCell::new(env));
@@ -77,6 +146,11 @@ impl Evaluator {
                     last_value = Value::None;
                 }
             }
+
+            // break, continue and return skip the rest of the block
+            if self.control_flow != ControlFlow::None {
+                break;
+            }
         }
 
         self.env = previous;
@@ -188,28 +262,46 @@ impl Evaluator {
                 let new_env = Environment::new_enclosed(self.env.clone());
                 self.execute_block(statements, new_env)
             }
-            Stmt::Let { name, initializer } => {
+            Stmt::Let { name, initializer, is_const } => {
                 let val = self.eval_expr(initializer)?;
                 if let Token::Identifier(n) = &name.token {
-                    self.env.borrow_mut().define(n.clone(), val);
+                    self.define_checked(&name, n.clone(), val, is_const)?;
+                    self.env.borrow_mut().set_trace(n, std::mem::take(&mut self.trace));
                 }
                 Ok(Value::None)
             }
-            Stmt::Symbolic { name, initializer, is_quiet } => {
+            Stmt::Symbolic { name, initializer, is_quiet, is_const } => {
                 if let Token::Identifier(n) = &name.token {
-                    self.env.borrow_mut().define(n.clone(), Value::Symbolic {
-                        expression: Box::new(initializer),
+                    let symbolic = Value::Symbolic {
+                        expression: Box::new(algebra::simplify(&initializer)),
                         is_quiet,
-                    });
+                    };
+                    self.define_checked(&name, n.clone(), symbolic, is_const)?;
                 }
                 Ok(Value::None)
             }
+            Stmt::Unknown { name, constraint } => {
+                let mut bounds = Bounds::new(constraint.clone());
+                self.constrain(&name, &constraint, &mut bounds)?;
+                if !bounds.is_satisfiable() {
+                    return Err(self.report_error(
+                        name.clone(),
+                        format!("Constraint on '{}' can never be satisfied", name.token_to_string()),
+                    ));
+                }
+                let mut trace = Trace::new();
+                trace.note(self.reporter.borrow().source_name(), &name, format!("declared as {}", Value::Bounded(bounds.clone())));
+                self.define_checked(&name, name.token_to_string(), Value::Bounded(bounds), false)?;
+                self.env.borrow_mut().set_trace(&name.token_to_string(), trace);
+                Ok(Value::None)
+            }
             Stmt::Assign { name, value } => {
                 let val = self.eval_expr(value)?;
                 if let Token::Identifier(n) = &name.token {
                     if let Err(msg) = self.env.borrow_mut().assign(n, val) {
                         return Err(self.report_error(name, msg));
                     }
+                    self.env.borrow_mut().set_trace(n, std::mem::take(&mut self.trace));
                 }
                 Ok(Value::None)
             }
@@ -231,40 +323,141 @@ impl Evaluator {
                 self.eval_if_chain(condition, *then_branch, &elif_branch, &else_branch, policy)
             }
             Stmt::Function { name, params, body, is_public } => {
-                let function = Value::Function(Function { params, body, closure: self.env.clone(), is_public });
+                let function = Value::Function(Function {
+                    params,
+                    body,
+                    closure: self.env.clone(),
+                    is_public,
+                    owner: self.method_owner.clone(),
+                });
                 self.env.borrow_mut().define(name.token_to_string(), function);
                 Ok(Value::None)
             }
+            Stmt::Struct { name, fields } => {
+                let def = StructDef {
+                    name: name.token_to_string(),
+                    fields,
+                    methods: RefCell::new(HashMap::new()),
+                    closure: self.env.clone(),
+                };
+                self.env.borrow_mut().define(name.token_to_string(), Value::Struct(Rc::new(def)));
+                Ok(Value::None)
+            }
+            Stmt::Impl { name, methods } => {
+                let def = match self.env.borrow().get(&name.token_to_string()) {
+                    Ok(Value::Struct(def)) => def,
+                    _ => return Err(self.report_error(
+                        name.clone(),
+                        format!("Cannot implement methods for '{}', it is not a struct", name.token_to_string()),
+                    )),
+                };
+
+                for method in methods {
+                    if let Stmt::Function { name, params, body, is_public } = method {
+                        let function = Function { params, body, closure: self.env.clone(), is_public, owner: None };
+                        def.methods.borrow_mut().insert(name.token_to_string(), function);
+                    }
+                }
+                Ok(Value::None)
+            }
             Stmt::Loop { body } => {
                 loop {
-                    self.control_flow = ControlFlow::None;
-                    let new_env = Environment::new_enclosed(self.env.clone());
-                    let previous = self.env.clone();
-                    self.env = Rc::new(RefCell::new(new_env));
-
-                    for stmt in body.clone() {
-                        if let Err(e) = self.eval_stmt(stmt) {
-                            self.env = previous.clone();
-                            return Err(e);
-                        }
-                        
-                        if self.control_flow == ControlFlow::Break {
-                            self.control_flow = ControlFlow::None;
-                            self.env = previous.clone();
-                            return Ok(Value::None);
+                    let iteration_env = Environment::new_enclosed(self.env.clone());
+                    if self.run_iteration(&body, iteration_env)? {
+                        return Ok(Value::None);
+                    }
+                }
+            }
+            Stmt::While { keyword, condition, policy, body } => {
+                let mut merged_iterations = None; // counted from the first uncertain check
+                // the part of a narrowed variable that already failed the condition and left the loop
+                let mut exited: Option<(String, Value)> = None;
+                loop {
+                    // a loop narrows for good, which a constant can't be
+                    let narrowing = if policy == IfPolicy::Merge { self.narrowing(&condition) } else { None }
+                        .filter(|n| !self.env.borrow().resolves_to_const(&n.name));
+                    let keep_going = match self.eval_expr(condition.clone())? {
+                        Value::Bool(SKBool::True) => true,
+                        Value::Bool(SKBool::False) => false,
+                        Value::Bool(b @ (SKBool::Partial | SKBool::Weighted(..))) => match policy {
+                            IfPolicy::Strict => false,
+                            IfPolicy::Likely(threshold) => matches!(b, SKBool::Weighted(p, _) if p > threshold),
+                            IfPolicy::Merge => {
+                                merged_iterations.get_or_insert(0);
+                                // only the part where the condition holds keeps looping
+                                if let Some(n) = narrowing {
+                                    let left = match exited.take() {
+                                        Some((_, before)) => self.join_values(before, n.else_value, &condition),
+                                        None => n.else_value,
+                                    };
+                                    self.env.borrow_mut().narrow(&n.name, n.then_value);
+                                    exited = Some((n.name, left));
+                                }
+                                true
+                            }
+                            IfPolicy::Panic => {
+                                return Err(self.report_error(
+                                    TokenSpan {
+                                        token: Token::Panic,
+                                        line: 0,
+                                        column: 0,
+                                    },
+                                    "Program panicked! Uncertain loop condition with panic policy",
+                                ));
+                            }
+                        },
+                        _ => {
+                            return Err(self.report_error(
+                                TokenSpan {
+                                    token: Token::Unknown,
+                                    line: 0,
+                                    column: 0,
+                                },
+                                "Condition must be a boolean",
+                            ));
                         }
-                        
-                        if self.control_flow == ControlFlow::Continue {
-                            break;
+                    };
+
+                    if !keep_going {
+                        break;
+                    }
+
+                    if let Some(count) = merged_iterations.as_mut() {
+                        *count += 1;
+                        if *count > MERGE_LOOP_LIMIT {
+                            return Err(self.report_error(
+                                keyword,
+                                format!("Merged loop did not settle after {} iterations", MERGE_LOOP_LIMIT),
+                            ));
                         }
                     }
-                    
-                    self.env = previous;
-                    
-                    if self.control_flow == ControlFlow::Continue {
-                        self.control_flow = ControlFlow::None;
+
+                    let iteration_env = Environment::new_enclosed(self.env.clone());
+                    if self.run_iteration(&body, iteration_env)? {
+                        break;
+                    }
+                }
+
+                if let Some((name, left)) = exited {
+                    let current = self.env.borrow().get(&name);
+                    let joined = match current {
+                        Ok(current) => self.join_values(left, current, &condition),
+                        Err(_) => left,
+                    };
+                    self.env.borrow_mut().narrow(&name, joined);
+                }
+                Ok(Value::None)
+            }
+            Stmt::For { name, iterable, end, step, body } => {
+                let items = self.for_items(&name, iterable, end, step)?;
+                for item in items {
+                    let mut iteration_env = Environment::new_enclosed(self.env.clone());
+                    iteration_env.define(name.token_to_string(), item);
+                    if self.run_iteration(&body, iteration_env)? {
+                        break;
                     }
                 }
+                Ok(Value::None)
             }
             Stmt::Break => {
                 self.control_flow = ControlFlow::Break;
@@ -274,9 +467,223 @@ impl Evaluator {
                 self.control_flow = ControlFlow::Continue;
                 Ok(Value::None)
             }
+            Stmt::Return { value, .. } => {
+                let value = match value {
+                    Some(expr) => self.eval_expr(expr)?,
+                    None => Value::None,
+                };
+                self.control_flow = ControlFlow::Return(value);
+                Ok(Value::None)
+            }
+        }
+    }
+
+    // Runs one pass of a loop body, returns true when the loop has to stop
+    fn run_iteration(&mut self, body: &[Stmt], env: Environment) -> Result<bool, Error> {
+        self.control_flow = ControlFlow::None;
+        let previous = self.env.clone();
+        self.env = Rc::new(RefCell::new(env));
+
+        for stmt in body {
+            if let Err(e) = self.eval_stmt(stmt.clone()) {
+                self.env = previous;
+                return Err(e);
+            }
+
+            if self.control_flow != ControlFlow::None {
+                break;
+            }
+        }
+
+        self.env = previous;
+
+        match self.control_flow {
+            ControlFlow::Break => {
+                self.control_flow = ControlFlow::None;
+                Ok(true)
+            }
+            ControlFlow::Return(_) => Ok(true), // the enclosing function takes care of it
+            _ => {
+                self.control_flow = ControlFlow::None;
+                Ok(false)
+            }
+        }
+    }
+
+    fn for_items(
+        &mut self,
+        name: &TokenSpan,
+        iterable: Expr,
+        end: Option<Box<Expr>>,
+        step: Option<Box<Expr>>,
+    ) -> Result<Box<dyn Iterator<Item = Value>>, Error> {
+        let start = self.eval_expr(iterable)?.plain();
+        let step = match step {
+            Some(expr) => match self.eval_expr(*expr)? {
+                Value::Number(n) if n != 0.0 => Some(n),
+                _ => return Err(self.report_error(name.clone(), "Loop step must be a non-zero number")),
+            },
+            None => None,
+        };
+
+        // for i in start..end, the end is not included
+        if let Some(end_expr) = end {
+            let (Value::Number(from), Value::Number(to)) = (start, self.eval_expr(*end_expr)?) else {
+                return Err(self.report_error(name.clone(), "Range bounds must be numbers"));
+            };
+            let step = step.unwrap_or(1.0);
+            if !(from.is_finite() && to.is_finite() && step.is_finite()) {
+                return Err(self.report_error(name.clone(), "Range bounds and step must be finite numbers"));
+            }
+            // values come one at a time, 0..1e9 doesn't build a billion of them first
+            let values = (0..).map(move |i| from + i as f64 * step);
+            let values = values.take_while(move |value| if step > 0.0 { *value < to } else { *value > to });
+            return Ok(Box::new(values.map(Value::Number)));
+        }
+
+        match start {
+            Value::List(items) => Ok(Box::new(items.borrow().clone().into_iter())),
+            Value::Map(entries) => {
+                let keys: Vec<Value> = entries.borrow().iter().map(|(k, _)| Value::String(k.clone())).collect();
+                Ok(Box::new(keys.into_iter()))
+            }
+            Value::String(s) => {
+                let chars: Vec<Value> = s.chars().map(|c| Value::String(c.to_string())).collect();
+                Ok(Box::new(chars.into_iter()))
+            }
+            Value::Interval(min, max) => {
+                let Some(step) = step.filter(|s| *s > 0.0) else {
+                    return Err(self.report_error(
+                        name.clone(),
+                        "Iterating over an interval needs a positive step, e.g. 'for x in [0..1] step 0.1'",
+                    ));
+                };
+                if !(min.is_finite() && max.is_finite() && step.is_finite()) {
+                    return Err(self.report_error(name.clone(), format!("Cannot iterate over '{}', its bounds aren't finite", start)));
+                }
+                // Using the index avoids accumulating rounding errors from repeated additions
+                let count = ((max - min) / step + 1e-9).floor() as u64;
+                Ok(Box::new((0..=count).map(move |i| Value::Number(min + i as f64 * step))))
+            }
+            other => Err(self.report_error(
+                name.clone(),
+                format!("Cannot iterate over '{}'", other),
+            )),
+        }
+    }
+
+    // Narrows the bounds of 'unknown x ...' with constraints like x > 0, 1 <= x, x != 3 or x % 2 == 0
+    fn constrain(&mut self, name: &TokenSpan, constraint: &Expr, bounds: &mut Bounds) -> Result<(), Error> {
+        let unsupported = format!(
+            "Unsupported constraint on '{}', expected something like '{0} > 0' or '{0} % 2 == 0'",
+            name.token_to_string()
+        );
+        let Expr::Binary { left, operator, right } = constraint else {
+            return Err(self.report_error(name.clone(), unsupported));
+        };
+
+        if operator.token == Token::And {
+            self.constrain(name, left, bounds)?;
+            return self.constrain(name, right, bounds);
+        }
+
+        let is_name = |expr: &Expr| matches!(expr, Expr::Variable { name: var } if var.token == name.token);
+
+        // x % m == r
+        if let Expr::Binary { left: inner, operator: rem_op, right: modulus } = left.as_ref()
+            && rem_op.token == Token::Percent
+            && operator.token == Token::EqualEqual
+            && is_name(inner)
+        {
+            let m = self.constraint_number(name, modulus)?.abs();
+            let r = self.constraint_number(name, right)?;
+            if m == 0.0 {
+                return Err(self.report_error(rem_op.clone(), "Modulo by zero!"));
+            }
+            if r < 0.0 || r >= m || bounds.modulus.is_some_and(|(old_m, old_r)| old_m == m && old_r != r) {
+                return Err(self.report_error(
+                    name.clone(),
+                    format!("Constraint on '{}' can never be satisfied", name.token_to_string()),
+                ));
+            }
+            if bounds.modulus.is_some_and(|(old_m, _)| old_m != m) {
+                return Err(self.report_error(
+                    rem_op.clone(),
+                    format!("Only one modulus constraint is supported on '{}'", name.token_to_string()),
+                ));
+            }
+            bounds.modulus = Some((m, r));
+            return Ok(());
+        }
+
+        let op = if is_name(left) {
+            operator.token.clone()
+        } else if is_name(right) {
+            match operator.token {
+                Token::Greater => Token::Less,
+                Token::Less => Token::Greater,
+                Token::GreaterEqual => Token::LessEqual,
+                Token::LessEqual => Token::GreaterEqual,
+                ref other => other.clone(),
+            }
+        } else {
+            return Err(self.report_error(name.clone(), unsupported));
+        };
+        let other = if is_name(left) { right } else { left };
+        let n = self.constraint_number(name, other)?;
+
+        let raise_min = |bounds: &mut Bounds, open: bool| {
+            if n > bounds.min.0 || (n == bounds.min.0 && open) {
+                bounds.min = (n, open);
+            }
+        };
+        let lower_max = |bounds: &mut Bounds, open: bool| {
+            if n < bounds.max.0 || (n == bounds.max.0 && open) {
+                bounds.max = (n, open);
+            }
+        };
+
+        match op {
+            Token::Greater => raise_min(bounds, true),
+            Token::GreaterEqual => raise_min(bounds, false),
+            Token::Less => lower_max(bounds, true),
+            Token::LessEqual => lower_max(bounds, false),
+            Token::EqualEqual => {
+                raise_min(bounds, false);
+                lower_max(bounds, false);
+            }
+            Token::BangEqual => bounds.excluded.push(n),
+            _ => return Err(self.report_error(name.clone(), unsupported)),
+        }
+        Ok(())
+    }
+
+    fn constraint_number(&mut self, name: &TokenSpan, expr: &Expr) -> Result<f64, Error> {
+        match self.eval_expr(expr.clone())? {
+            Value::Number(n) => Ok(n),
+            other => Err(self.report_error(
+                name.clone(),
+                format!("Constraints can only compare '{}' with numbers, got '{}'", name.token_to_string(), other),
+            )),
         }
     }
 
+    fn define_checked(&mut self, span: &TokenSpan, name: String, value: Value, is_const: bool) -> Result<(), Error> {
+        if self.env.borrow().is_const(&name) {
+            return Err(self.report_error(
+                span.clone(),
+                format!("Cannot redeclare constant '{}'", name),
+            ));
+        }
+
+        if is_const {
+            self.env.borrow_mut().define_const(name, value);
+        } else {
+            self.env.borrow_mut().define(name, value);
+        }
+        Ok(())
+    }
+
     fn print_value(&mut self, val: Value) {
         match val {
             Value::Symbolic { ref expression, is_quiet } => {
@@ -287,7 +694,7 @@ impl Evaluator {
                         println!("Error resolving quiet symbolic");
                     }
                 } else {
-                    println!("{}", self.format_symbolic(expression));
+                    println!("{}", val);
                 }
             }
             _ => println!("{}", val),
@@ -302,6 +709,8 @@ impl Evaluator {
         else_branch: &Option<Box<Stmt>>,
         policy: IfPolicy,
     ) -> Result<Value, Error> {
+        let cond_copy = cond_expr.clone();
+        let narrowing = if policy == IfPolicy::Merge { self.narrowing(&cond_expr) } else { None };
         let cond_val = self.eval_expr(cond_expr)?;
         let sk_bool = match cond_val {
             Value::Bool(b) => b,
@@ -320,21 +729,146 @@ impl Evaluator {
         match sk_bool {
             SKBool::True => self.eval_stmt(body),
             SKBool::False => self.eval_next_in_chain(remaining_elifs, else_branch, policy),
-            SKBool::Partial => match policy {
-                IfPolicy::Strict => self.eval_next_in_chain(remaining_elifs, &None, policy),
+            // likely(p) treats the condition as true or false depending on its estimate
+            SKBool::Weighted(p, _) if matches!(policy, IfPolicy::Likely(threshold) if p > threshold) => self.eval_stmt(body),
+            SKBool::Weighted(..) if matches!(policy, IfPolicy::Likely(_)) => {
+                self.eval_next_in_chain(remaining_elifs, else_branch, policy)
+            }
+            SKBool::Partial | SKBool::Weighted(..) => match policy {
+                IfPolicy::Strict | IfPolicy::Likely(_) => self.eval_next_in_chain(remaining_elifs, &None, policy),
                 IfPolicy::Panic => {
                     eprintln!("Program panicked! Uncertain condition with panic policy");
                     std::process::exit(1);
                 }
                 IfPolicy::Merge => {
-                    let val_true = self.eval_stmt(body)?;
-                    let val_false = self.eval_next_in_chain(remaining_elifs, else_branch, policy)?;
-                    self.merge_values(val_true, val_false)
+                    let cond_trace = std::mem::take(&mut self.trace);
+                    let then_narrowing = narrowing.as_ref().map(|n| (n.name.as_str(), n.then_value.clone()));
+                    let (val_true, exit_true) = self.with_narrowed(then_narrowing, |this| this.eval_stmt(body))?;
+                    let flow_true = std::mem::replace(&mut self.control_flow, ControlFlow::None);
+                    let trace_true = std::mem::take(&mut self.trace);
+
+                    let else_narrowing = narrowing.as_ref().map(|n| (n.name.as_str(), n.else_value.clone()));
+                    let (val_false, exit_false) = self.with_narrowed(else_narrowing, |this| {
+                        this.eval_next_in_chain(remaining_elifs, else_branch, policy)
+                    })?;
+                    let flow_false = std::mem::replace(&mut self.control_flow, ControlFlow::None);
+
+                    // the variable ends up covering whatever either branch left it at
+                    if let (Some(n), Some(exit_true), Some(exit_false)) = (&narrowing, exit_true, exit_false)
+                        && !self.env.borrow().resolves_to_const(&n.name)
+                    {
+                        let joined = self.join_values(exit_true, exit_false, &cond_copy);
+                        self.env.borrow_mut().narrow(&n.name, joined);
+                    }
+                    // the merged value is uncertain because of the condition, and of whatever each branch did
+                    let mut trace = cond_trace;
+                    trace.join(trace_true);
+                    trace.join(std::mem::take(&mut self.trace));
+                    self.trace = trace;
+
+                    match (flow_true, flow_false) {
+                        // Both branches return, so the function returns the merge of both values
+                        (ControlFlow::Return(r1), ControlFlow::Return(r2)) => {
+                            let merged = self.merge_values(r1, r2, &cond_copy)?;
+                            self.control_flow = ControlFlow::Return(merged);
+                            Ok(Value::None)
+                        }
+                        // Only one side returns, its value waits for whatever the function returns later
+                        (ControlFlow::Return(returned), flow) => {
+                            self.pending_returns.push((self.freeze(cond_copy), returned));
+                            self.control_flow = flow;
+                            Ok(val_false)
+                        }
+                        (flow, ControlFlow::Return(returned)) => {
+                            let negated = Expr::Unary {
+                                operator: TokenSpan { token: Token::Bang, ..Self::span_of(&cond_copy) },
+                                right: Box::new(Expr::Grouping { expression: Box::new(cond_copy) }),
+                            };
+                            self.pending_returns.push((self.freeze(negated), returned));
+                            self.control_flow = flow;
+                            Ok(val_true)
+                        }
+                        (flow, ControlFlow::None) | (ControlFlow::None, flow) | (flow, _) => {
+                            self.control_flow = flow;
+                            self.merge_values(val_true, val_false, &cond_copy)
+                        }
+                    }
                 }
             },
         }
     }
 
+    // Only 'variable <op> constant' (or the other way around) on an interval variable narrows anything
+    fn narrowing(&mut self, cond: &Expr) -> Option<Narrowing> {
+        let Expr::Binary { left, operator, right } = cond else { return None };
+
+        let (name, op, constant) = match (left.as_ref(), right.as_ref()) {
+            (Expr::Variable { name }, other) => (name, operator.token.clone(), other),
+            (other, Expr::Variable { name }) => {
+                let flipped = match operator.token {
+                    Token::Greater => Token::Less,
+                    Token::Less => Token::Greater,
+                    Token::GreaterEqual => Token::LessEqual,
+                    Token::LessEqual => Token::GreaterEqual,
+                    ref other => other.clone(),
+                };
+                (name, flipped, other)
+            }
+            _ => return None,
+        };
+
+        let name = name.token_to_string();
+        let original = self.env.borrow().get(&name).ok()?;
+        let Value::Interval(min, max) = original.clone().plain() else {
+            return None;
+        };
+        let c = match constant {
+            Expr::Literal { value: TokenSpan { token: Token::Number(n), .. } } => *n,
+            Expr::Unary { operator: TokenSpan { token: Token::Minus, .. }, right } => match right.as_ref() {
+                Expr::Literal { value: TokenSpan { token: Token::Number(n), .. } } => -n,
+                _ => return None,
+            },
+            Expr::Variable { name } => match self.env.borrow().get(&name.token_to_string()) {
+                Ok(Value::Number(n)) => n,
+                _ => return None,
+            },
+            _ => return None,
+        };
+        if c < min || c > max {
+            return None;
+        }
+
+        let (then_value, else_value) = match op {
+            Token::Greater | Token::GreaterEqual => (Value::Interval(c, max), Value::Interval(min, c)),
+            Token::Less | Token::LessEqual => (Value::Interval(min, c), Value::Interval(c, max)),
+            Token::EqualEqual => (Value::Number(c), original.clone()),
+            _ => return None,
+        };
+
+        Some(Narrowing { name, then_value, else_value })
+    }
+
+    // Runs a branch with a variable narrowed, then puts the original back and hands out what the branch left it at
+    fn with_narrowed(
+        &mut self,
+        narrowed: Option<(&str, Value)>,
+        run: impl FnOnce(&mut Self) -> Result<Value, Error>,
+    ) -> Result<(Value, Option<Value>), Error> {
+        let Some((name, value)) = narrowed else {
+            return Ok((run(self)?, None));
+        };
+
+        let original = self.env.borrow().get(name);
+        self.env.borrow_mut().narrow(name, value);
+        let result = run(self);
+        let exit = self.env.borrow().get(name).ok();
+
+        if let Ok(original) = original {
+            self.env.borrow_mut().narrow(name, original);
+        }
+        Ok((result?, exit))
+    }
+
     fn eval_next_in_chain(
         &mut self,
         elifs: &[(Expr, Stmt)],
@@ -350,73 +884,278 @@ impl Evaluator {
         }
     }
 
-    // Not fully implemented
-    fn merge_values(&mut self, v1: Value, v2: Value) -> Result<Value, Error> {
-        match (v1, v2) {
-            (Value::Number(n1), Value::Number(n2)) => Ok(Value::Interval(n1.min(n2), n1.max(n2))),
-            (Value::Interval(l1, h1), Value::Interval(l2, h2)) => Ok(Value::Interval(l1.min(l2), h1.max(h2))),
+    fn merge_values(&mut self, v1: Value, v2: Value, cond: &Expr) -> Result<Value, Error> {
+        let (before_1, before_2) = (v1.to_string(), v2.to_string());
+        let merged = self.join_values(v1, v2, cond);
+        if before_1 != before_2 {
+            // column 0 keeps it apart from the condition's own event on the same token
+            let at = TokenSpan { column: 0, ..Self::span_of(cond) };
+            self.note(&at, format!("an uncertain if merged {} and {} into {}", before_1, before_2, merged));
+        }
+        Ok(merged)
+    }
+
+    // The least value that covers both branches
+    fn join_values(&self, v1: Value, v2: Value, cond: &Expr) -> Value {
+        match (v1.plain(), v2.plain()) {
+            (a, b) if a == b => a,
+            (Value::Number(n1), Value::Number(n2)) => Value::Interval(n1.min(n2), n1.max(n2)),
+            (Value::Interval(l1, h1), Value::Interval(l2, h2)) => Value::Interval(l1.min(l2), h1.max(h2)),
             (Value::Number(n), Value::Interval(l, h)) | (Value::Interval(l, h), Value::Number(n)) => {
-                Ok(Value::Interval(n.min(l), n.max(h)))
+                Value::Interval(n.min(l), n.max(h))
             }
-            (a, b) if a == b => Ok(a),
-            _ => Ok(Value::Unknown),
+            (Value::Bool(_), Value::Bool(_)) => Value::Bool(SKBool::Partial),
+            (Value::Unknown, _) | (_, Value::Unknown) => Value::Unknown,
+
+            // symbolic branches keep both formulas, picked by the condition once it is known
+            (a, b) if matches!(a, Value::Symbolic { .. }) || matches!(b, Value::Symbolic { .. }) => {
+                let is_quiet = matches!(a, Value::Symbolic { is_quiet: true, .. })
+                    || matches!(b, Value::Symbolic { is_quiet: true, .. });
+                Value::Symbolic {
+                    expression: Box::new(Expr::Conditional {
+                        condition: Box::new(self.freeze(cond.clone())),
+                        then_branch: Box::new(self.value_to_expr(a)),
+                        else_branch: Box::new(self.value_to_expr(b)),
+                    }),
+                    is_quiet,
+                }
+            }
+
+            // strings, none, lists... the result is one of the two
+            (a, b) => Value::one_of(vec![a, b]),
         }
     }
 
-    fn format_symbolic(&self, expr: &Expr) -> String {
+    // Swaps variables for their current values, the branch's locals are gone by the time it is resolved
+    fn freeze(&self, expr: Expr) -> Expr {
         match expr {
-            Expr::Binary { left, operator, right } => {
-                let l = self.format_symbolic(left);
-                let r = self.format_symbolic(right);
-                let op = match operator.token {
-                    Token::Plus => "+",
-                    Token::Minus => "-",
-                    Token::Star => "*",
-                    Token::Slash => "/",
-                    Token::Caret => "^",
-                    Token::EqualEqual => "==",
-                    Token::BangEqual => "!=",
-                    Token::Greater => ">",
-                    Token::GreaterEqual => ">=",
-                    Token::Less => "<",
-                    Token::LessEqual => "<=",
-                    Token::And => "&&",
-                    Token::Or => "||",
-                    _ => "?",
+            Expr::Variable { ref name } => match self.env.borrow().get(&name.token_to_string()) {
+                Ok(value @ (Value::Number(_) | Value::Interval(..) | Value::Bool(_) | Value::String(_))) => {
+                    self.value_to_expr(value)
+                }
+                _ => expr,
+            },
+            Expr::Binary { left, operator, right } => Expr::Binary {
+                left: Box::new(self.freeze(*left)),
+                operator,
+                right: Box::new(self.freeze(*right)),
+            },
+            Expr::Grouping { expression } => Expr::Grouping { expression: Box::new(self.freeze(*expression)) },
+            Expr::Unary { operator, right } => Expr::Unary { operator, right: Box::new(self.freeze(*right)) },
+            other => other,
+        }
+    }
+
+    // Works out every part of a formula whose variables are known, the rest stays symbolic
+    pub fn partial(&mut self, expr: &Expr, visiting: &mut Vec<String>) -> Expr {
+        let expr = match expr {
+            Expr::Variable { name } => {
+                let name = name.token_to_string();
+                let value = self.env.borrow().get(&name);
+                return match value {
+                    Ok(value @ (Value::Number(_) | Value::Interval(..) | Value::Affine(_) | Value::Bool(_) | Value::String(_))) => {
+                        self.value_to_expr(value)
+                    }
+                    Ok(Value::Symbolic { expression, .. }) if !visiting.contains(&name) => {
+                        visiting.push(name);
+                        let formula = self.partial(&expression, visiting);
+                        visiting.pop();
+                        formula
+                    }
+                    _ => expr.clone(),
                 };
-                format!("({} {} {})", l, op, r)
-            }
-            Expr::Literal { value } => match &value.token {
-                Token::Number(n) => format!("{}", n),
-                Token::Unknown => "unknown".to_string(),
-                Token::String(s) => s.clone(),
-                Token::True => "true".to_string(),
-                Token::False => "false".to_string(),
-                Token::Partial => "partial".to_string(),
-                _ => format!("{:?}", value.token),
+            }
+            Expr::Binary { left, operator, right } => Expr::Binary {
+                left: Box::new(self.partial(left, visiting)),
+                operator: operator.clone(),
+                right: Box::new(self.partial(right, visiting)),
+            },
+            Expr::Unary { operator, right } => Expr::Unary { operator: operator.clone(), right: Box::new(self.partial(right, visiting)) },
+            Expr::Grouping { expression } => return self.partial(expression, visiting),
+            Expr::Call { callee, paren, arguments } => Expr::Call {
+                callee: callee.clone(),
+                paren: paren.clone(),
+                arguments: arguments.iter().map(|a| self.partial(a, visiting)).collect(),
+            },
+            Expr::Interval { min, max, bracket } => Expr::Interval {
+                min: Box::new(self.partial(min, visiting)),
+                max: Box::new(self.partial(max, visiting)),
+                bracket: bracket.clone(),
+            },
+            Expr::Conditional { condition, then_branch, else_branch } => Expr::Conditional {
+                condition: Box::new(self.partial(condition, visiting)),
+                then_branch: Box::new(self.partial(then_branch, visiting)),
+                else_branch: Box::new(self.partial(else_branch, visiting)),
+            },
+            Expr::Get { object, name } => Expr::Get { object: Box::new(self.partial(object, visiting)), name: name.clone() },
+            Expr::Index { object, index, bracket } => Expr::Index {
+                object: Box::new(self.partial(object, visiting)),
+                index: Box::new(self.partial(index, visiting)),
+                bracket: bracket.clone(),
+            },
+            Expr::OneOf { options, brace } => Expr::OneOf {
+                options: options.iter().map(|o| self.partial(o, visiting)).collect(),
+                brace: brace.clone(),
+            },
+            other => return other.clone(),
+        };
+
+        let known = |e: &Expr| match e {
+            Expr::Literal { value } => value.token != Token::Unknown,
+            Expr::Interval { min, max, .. } => matches!((min.as_ref(), max.as_ref()), (Expr::Literal { .. }, Expr::Literal { .. })),
+            _ => false,
+        };
+        let parts_known = match &expr {
+            Expr::Binary { left, right, .. } => known(left) && known(right),
+            Expr::Unary { right, .. } => known(right),
+            Expr::Call { arguments, .. } => arguments.iter().all(known),
+            Expr::OneOf { options, .. } => options.iter().all(known),
+            Expr::Get { object, .. } => self.is_container(object),
+            Expr::Index { object, index, .. } => self.is_container(object) && known(index),
+            _ => false,
+        };
+        if !parts_known {
+            return expr;
+        }
+        match self.eval_expr(expr.clone()) {
+            Ok(value @ (Value::Number(_) | Value::Interval(..) | Value::Affine(_) | Value::Bool(_) | Value::String(_))) => self.value_to_expr(value),
+            _ => expr,
+        }
+    }
+
+    // Whether a field or an element can be read out of expr without guessing, p in p.x or l in l[0]
+    fn is_container(&self, expr: &Expr) -> bool {
+        match expr {
+            Expr::Variable { name } => matches!(
+                self.env.borrow().get(&name.token_to_string()),
+                Ok(Value::Map(_) | Value::List(_) | Value::Instance(_) | Value::Module(_))
+            ),
+            Expr::Get { object, .. } => self.is_container(object),
+            Expr::Index { object, index, .. } => self.is_container(object) && matches!(index.as_ref(), Expr::Literal { .. }),
+            _ => false,
+        }
+    }
+
+    pub fn value_to_expr(&self, value: Value) -> Expr {
+        let literal = |token| Expr::Literal { value: TokenSpan { token, line: 0, column: 0 } };
+        match value.plain() {
+            Value::Symbolic { expression, .. } => *expression,
+            Value::Interval(min, max) => Expr::Interval {
+                min: Box::new(literal(Token::Number(min))),
+                max: Box::new(literal(Token::Number(max))),
+                bracket: TokenSpan { token: Token::LBracket, line: 0, column: 0 },
             },
+            other => literal(self.value_to_token(other)),
+        }
+    }
+
+    fn operator_symbol(token: &Token) -> &'static str {
+        match token {
+            Token::Plus => "+",
+            Token::Minus => "-",
+            Token::Star => "*",
+            Token::Slash => "/",
+            Token::Caret => "^",
+            Token::Percent => "%",
+            Token::EqualEqual => "==",
+            Token::BangEqual => "!=",
+            Token::Greater => ">",
+            Token::GreaterEqual => ">=",
+            Token::Less => "<",
+            Token::LessEqual => "<=",
+            Token::And => "&&",
+            Token::Or => "||",
+            _ => "?",
+        }
+    }
+
+    // Somewhere to point at when an expression as a whole did something
+    fn span_of(expr: &Expr) -> TokenSpan {
+        match expr {
+            Expr::Binary { operator, .. } | Expr::Unary { operator, .. } => operator.clone(),
+            Expr::Literal { value } => value.clone(),
+            Expr::Variable { name } | Expr::Postfix { name, .. } | Expr::Get { name, .. } => name.clone(),
+            Expr::Grouping { expression } => Self::span_of(expression),
+            Expr::Call { paren, .. } => paren.clone(),
+            Expr::Interval { bracket, .. } | Expr::List { bracket, .. } | Expr::Index { bracket, .. } => bracket.clone(),
+            Expr::Map { brace, .. } => brace.clone(),
+            _ => TokenSpan { token: Token::Unknown, line: 0, column: 0 },
+        }
+    }
+
+    // Records the operations that made a result less certain than what went into it
+    fn note_binary(&mut self, op: &TokenSpan, left: &Value, right: &Value, result: &Value) {
+        let symbol = Self::operator_symbol(&op.token);
+        match result {
+            Value::Interval(min, max) => {
+                let width = |v: &Value| match v {
+                    Value::Interval(a, b) => b - a,
+                    _ => 0.0,
+                };
+                if max - min > width(left).max(width(right)) {
+                    self.note(op, format!("{} {} {} widened the range to {}", left, symbol, right, result));
+                }
+            }
+            Value::Bool(SKBool::Partial | SKBool::Weighted(..)) if matches!(
+                op.token,
+                Token::Greater | Token::GreaterEqual | Token::Less | Token::LessEqual | Token::EqualEqual | Token::BangEqual
+            ) => {
+                self.note(op, format!("{} {} {} can go either way", left, symbol, right));
+            }
+            _ => {}
+        }
+    }
+
+    // The formula held by a symbolic variable, if that's what the name is
+    pub fn symbolic_formula(&self, name: &str) -> Option<Expr> {
+        match self.env.borrow().get(name) {
+            Ok(Value::Symbolic { expression, .. }) => Some(*expression),
+            _ => None,
+        }
+    }
+
+    // Names of the unknowns and intervals a symbolic formula still depends on
+    pub fn symbolic_dependencies(&self, expr: &Expr) -> Vec<String> {
+        let mut found = Vec::new();
+        self.collect_dependencies(expr, &mut found, &mut Vec::new());
+        found
+    }
+
+    fn collect_dependencies(&self, expr: &Expr, found: &mut Vec<String>, visited: &mut Vec<String>) {
+        match expr {
             Expr::Variable { name } => {
-                if let Token::Identifier(n) = &name.token {
-                    n.clone()
-                } else {
-                    format!("{:?}", name.token)
+                let name = name.token_to_string();
+                if visited.contains(&name) {
+                    return;
+                }
+                visited.push(name.clone());
+                match self.env.borrow().get(&name) {
+                    Ok(Value::Symbolic { expression, .. }) => self.collect_dependencies(&expression, found, visited),
+                    Ok(Value::Unknown | Value::Bounded(_) | Value::Interval(..)) | Err(_) => found.push(name),
+                    _ => {}
                 }
             }
-            Expr::Grouping { expression } => format!("({})", self.format_symbolic(expression)),
-            Expr::Block { .. } => "{...}".to_string(),
-            Expr::Postfix { name, operator } => {
-                let n = match &name.token {
-                    Token::Identifier(s) => s.as_str(),
-                    _ => "?",
-                };
-                let op = match operator.token {
-                    Token::Increment => "++",
-                    Token::Decrement => "--",
-                    _ => "?",
-                };
-                format!("{}{}", n, op)
+            Expr::Literal { value } if value.token == Token::Unknown && !found.iter().any(|n| n == "unknown") => {
+                found.push("unknown".to_string());
             }
-            _ => "...".to_string(),
+            Expr::Binary { left, right, .. } => {
+                self.collect_dependencies(left, found, visited);
+                self.collect_dependencies(right, found, visited);
+            }
+            Expr::Grouping { expression } | Expr::Unary { right: expression, .. } => {
+                self.collect_dependencies(expression, found, visited)
+            }
+            Expr::Call { arguments, .. } => {
+                for arg in arguments {
+                    self.collect_dependencies(arg, found, visited);
+                }
+            }
+            Expr::Conditional { condition, then_branch, else_branch } => {
+                self.collect_dependencies(condition, found, visited);
+                self.collect_dependencies(then_branch, found, visited);
+                self.collect_dependencies(else_branch, found, visited);
+            }
+            _ => {}
         }
     }
 
@@ -426,14 +1165,16 @@ impl Evaluator {
             Value::String(s) => Token::String(s),
             Value::Bool(SKBool::True) => Token::True,
             Value::Bool(SKBool::False) => Token::False,
-            Value::Bool(SKBool::Partial) => Token::Partial,
+            Value::Bool(SKBool::Partial | SKBool::Weighted(..)) => Token::Partial,
             Value::Unknown => Token::Unknown,
+            Value::Empty => Token::Empty,
             Value::None => Token::None,
             _ => Token::Unknown,
         }
     }
 
     fn eval_expr(&mut self, expr: Expr) -> Result<Value, Error> {
+        self.trace = Trace::new();
         match expr {
             Expr::Block { statements } => {
                 let new_env = Environment::new_enclosed(self.env.clone());
@@ -445,9 +1186,16 @@ impl Evaluator {
                 Token::String(s) => Ok(Value::String(s)),
                 Token::True => Ok(Value::Bool(SKBool::True)),
                 Token::False => Ok(Value::Bool(SKBool::False)),
-                Token::Partial => Ok(Value::Bool(SKBool::Partial)),
-                Token::Unknown => Ok(Value::Unknown),
+                Token::Partial => {
+                    self.note(&value, "written as partial");
+                    Ok(Value::Bool(SKBool::Partial))
+                }
+                Token::Unknown => {
+                    self.note(&value, "declared as unknown");
+                    Ok(Value::Unknown)
+                }
                 Token::None => Ok(Value::None),
+                Token::Empty => Ok(Value::Empty),
                 _ => Err(self.report_error(value, "Unsupported literal")),
             },
 
@@ -465,10 +1213,12 @@ impl Evaluator {
                     Token::Num => "num",
                     _ => return Err(self.report_error(name, "Expected identifier")),
                 };
-                self.env
+                let value = self.env
                     .borrow()
                     .get(name_str)
-                    .map_err(|msg| self.report_error(name, msg))
+                    .map_err(|msg| self.report_error(name.clone(), msg))?;
+                self.trace = self.env.borrow().trace(name_str);
+                Ok(value)
             }
 
             Expr::Postfix { name, operator } => {
@@ -483,6 +1233,7 @@ impl Evaluator {
                     .get(&name_str)
                     .map_err(|msg| self.report_error(name.clone(), msg))?;
 
+                let current = current.widen();
                 let one = Value::Number(1.0);
                 let updated = if current == Value::Unknown {
                     Value::Unknown
@@ -531,7 +1282,14 @@ impl Evaluator {
                 let low = self.eval_expr(*min)?;
                 let high = self.eval_expr(*max)?;
                 match (low, high) {
-                    (Value::Number(l), Value::Number(h)) => Ok(Value::Interval(l, h)),
+                    (Value::Number(l), Value::Number(h)) => {
+                        self.trace = Trace::new();
+                        self.note(&bracket, format!("written as the interval [{}..{}]", l, h));
+                        if settings::current().affine {
+                            return Ok(Value::Affine(Affine::new(l, h)));
+                        }
+                        Ok(Value::Interval(l, h))
+                    }
                     _ => Err(self.report_error(
                         bracket,
                         "Interval bounds must be numbers",
@@ -545,83 +1303,189 @@ impl Evaluator {
                 right,
             } => {
                 let l_val = self.eval_expr(*left)?;
+                let mut trace = std::mem::take(&mut self.trace);
                 let r_val = self.eval_expr(*right)?;
+                trace.join(std::mem::take(&mut self.trace));
+                self.trace = trace;
                 self.apply_binary(l_val, operator, r_val)
             }
 
             Expr::Unary { operator, right } => {
                 let val = self.eval_expr(*right)?;
+                // -x of a constrained unknown only keeps its range, like any other arithmetic on it
+                let val = if operator.token == Token::Minus { val.widen() } else { val };
                 match (operator.token.clone(), val) {
                     (Token::Minus, Value::Number(n)) => Ok(Value::Number(-n)),
                     (Token::Bang, Value::Bool(b)) => Ok(Value::Bool(logic::not(b))),
+                    (_, Value::Symbolic { expression, is_quiet }) => Ok(Value::Symbolic {
+                        expression: Box::new(algebra::simplify(&Expr::Unary { operator, right: expression })),
+                        is_quiet,
+                    }),
+                    (Token::Minus, Value::Unknown) => Ok(Value::Unknown),
+                    (Token::Minus, val @ (Value::Interval(..) | Value::Affine(_) | Value::Union(_) | Value::Dist(_) | Value::OneOf(_))) => {
+                        Value::Number(0.0).sub(&val).map_err(|e| self.report_error(operator, e.message))
+                    }
                     _ => Err(self.report_error(operator, "Invalid unary operation")),
                 }
             }
 
             Expr::Grouping { expression } => self.eval_expr(*expression),
             Expr::Call { callee, arguments, paren } => {
+                let callee_expr = (*callee).clone();
                 let callee_val = self.eval_expr(*callee)?;
 
+                // solve() gets its equation as written, lhs == rhs over unknowns would only be partial once evaluated
+                let takes_equation = matches!(callee_val, Value::NativeFn(func) if builtins::takes_equation(func));
+
                 let mut eval_args = Vec::new();
-                for arg in &arguments {
-                    eval_args.push(self.eval_expr(arg.clone())?);
+                let mut arg_traces = Vec::new();
+                for (i, arg) in arguments.iter().enumerate() {
+                    let value = if takes_equation && i == 0 {
+                        let formula = self.partial(arg, &mut Vec::new());
+                        Value::Symbolic { expression: Box::new(algebra::simplify(&formula)), is_quiet: false }
+                    } else {
+                        self.eval_expr(arg.clone())?
+                    };
+                    eval_args.push(value);
+                    arg_traces.push(std::mem::take(&mut self.trace));
                 }
 
-                match callee_val {
-                    Value::NativeFn(func) => {
-                        match func(eval_args, paren.clone(), self) {
-                            Ok(v) => Ok(v),
-                            Err(mut e) => {
-                                if matches!(e.token.token, Token::Unknown) {
-                                    e.token = paren;
-                                }
-                                Err(e)
-                            }
-                        }
-                    },
-                    Value::Function(func) => {
-                        let mut call_env = Environment::new_enclosed(func.closure.clone());
-
-                        for (i, param) in func.params.iter().enumerate() {
-                            let value = if i < eval_args.len() {
-                                eval_args[i].clone()
-                            } else if let Some(default_expr) = &param.default {
-                                self.eval_expr(default_expr.clone())?
-                            } else {
-                                return Err(self.report_error(
-                                    paren.clone(),
-                                    format!(
-                                        "Missing required argument '{}'",
-                                        param.name.token_to_string()
-                                    ),
-                                ));
-                            };
+                // The result is as uncertain as the arguments that went in
+                self.arg_traces = arg_traces.clone();
+                let result = self.call_written(callee_val, eval_args, paren, Some(callee_expr))?;
+                let mut trace = Trace::new();
+                for arg_trace in arg_traces {
+                    trace.join(arg_trace);
+                }
+                trace.join(std::mem::take(&mut self.trace));
+                self.trace = trace;
+                Ok(result)
+            }
 
-                            call_env.define(param.name.token_to_string(), value);
-                        }
+            Expr::Conditional { condition, then_branch, else_branch } => {
+                let cond_copy = (*condition).clone();
+                match self.eval_expr(*condition)? {
+                    Value::Bool(SKBool::True) => self.eval_expr(*then_branch),
+                    Value::Bool(SKBool::False) => self.eval_expr(*else_branch),
+                    _ => {
+                        let then_value = self.eval_expr(*then_branch)?;
+                        let else_value = self.eval_expr(*else_branch)?;
+                        Ok(self.join_values(then_value, else_value, &cond_copy))
+                    }
+                }
+            }
+
+            Expr::Lambda { params, body } => Ok(Value::Function(Function {
+                params,
+                body,
+                closure: self.env.clone(),
+                is_public: false,
+                owner: self.method_owner.clone(),
+            })),
+
+            Expr::List { elements, .. } => {
+                let mut items = Vec::new();
+                for element in elements {
+                    items.push(self.eval_expr(element)?);
+                }
+                Ok(Value::list(items))
+            }
 
-                        if eval_args.len() > func.params.len() {
+            Expr::Index { object, index, bracket } => {
+                let obj_value = self.eval_expr(*object)?;
+                let index_value = self.eval_expr(*index)?;
+                match (obj_value, index_value) {
+                    (Value::List(items), Value::Number(n)) => {
+                        let items = items.borrow();
+                        if n.fract() != 0.0 || n < 0.0 || n as usize >= items.len() {
                             return Err(self.report_error(
-                                paren,
-                                format!(
-                                    "Expected at most {} args, got {}",
-                                    func.params.len(),
-                                    eval_args.len()
-                                ),
+                                bracket,
+                                format!("Index {} out of bounds for list of length {}", n, items.len()),
                             ));
                         }
-
-                        self.execute_block(func.body.clone(), call_env)
+                        Ok(items[n as usize].clone())
                     }
-                    _ => Err(self.report_error(
-                        paren,
-                        format!("Value '{}' is not callable", callee_val),
+                    (Value::List(_), _) => Err(self.report_error(bracket, "List index must be a number")),
+                    (Value::Map(entries), Value::String(key)) => {
+                        let found = entries.borrow().iter().find(|(k, _)| *k == key).map(|(_, v)| v.clone());
+                        found.ok_or_else(|| self.report_error(bracket, format!("Map has no field '{}'", key)))
+                    }
+                    (Value::Map(_), _) => Err(self.report_error(bracket, "Map keys must be strings")),
+                    (other, _) => Err(self.report_error(
+                        bracket,
+                        format!("Value '{}' cannot be indexed", other),
                     )),
                 }
             }
 
+            Expr::OneOf { options, .. } => {
+                let mut values = Vec::new();
+                for option in options {
+                    values.push(self.eval_expr(option)?);
+                }
+                Ok(Value::one_of(values))
+            }
+
+            Expr::Map { entries, .. } => {
+                let mut fields: Vec<(String, Value)> = Vec::new();
+                for (key, value_expr) in entries {
+                    let value = self.eval_expr(value_expr)?;
+                    let key = key.token_to_string();
+                    match fields.iter_mut().find(|(k, _)| *k == key) {
+                        Some(field) => field.1 = value,
+                        None => fields.push((key, value)),
+                    }
+                }
+                Ok(Value::map(fields))
+            }
+
+            Expr::Set { object, name, value } => {
+                let obj_value = self.eval_expr(*object)?;
+                let value = self.eval_expr(*value)?;
+                let field = name.token_to_string();
+                let value = value.stored_in(&obj_value);
+                match obj_value {
+                    Value::Map(entries) => {
+                        let mut entries = entries.borrow_mut();
+                        match entries.iter_mut().find(|(k, _)| *k == field) {
+                            Some(entry) => entry.1 = value,
+                            None => entries.push((field, value)),
+                        }
+                        Ok(Value::None)
+                    }
+                    Value::Instance(instance) => {
+                        let def = instance.borrow().def.clone();
+                        match def.fields.iter().find(|f| f.name.token_to_string() == field) {
+                            Some(f) if !f.is_public && !self.inside_methods_of(&def) => {
+                                Err(self.report_error(name, format!("Field '{}' is private!", field)))
+                            }
+                            Some(_) => {
+                                let mut instance = instance.borrow_mut();
+                                if let Some(entry) = instance.fields.iter_mut().find(|(k, _)| *k == field) {
+                                    entry.1 = value;
+                                }
+                                Ok(Value::None)
+                            }
+                            None => Err(self.report_error(
+                                name,
+                                format!("'{}' has no field '{}'", def.name, field),
+                            )),
+                        }
+                    }
+                    _ => Err(self.report_error(name, "Only maps and structs have assignable fields!")),
+                }
+            }
+
             Expr::Get { object, name } => {
                 let obj_value = self.eval_expr(*object)?;
+                if let Value::Map(entries) = &obj_value {
+                    let field = name.token_to_string();
+                    let found = entries.borrow().iter().find(|(k, _)| *k == field).map(|(_, v)| v.clone());
+                    return found.ok_or_else(|| self.report_error(name, format!("Map has no field '{}'", field)));
+                }
+                if let Value::Instance(_) | Value::Struct(_) = obj_value {
+                    return self.get_member(obj_value, name);
+                }
                 if let Value::Module(mod_env) = obj_value {
                     let member_name = match &name.token {
                         Token::Identifier(s) => s,
@@ -634,54 +1498,359 @@ impl Evaluator {
                         .map_err(|msg| self.report_error(name.clone(), msg))?;
 
                     // Check if private
-                    if let Value::Function(func) = &val {
-                        if !func.is_public {
-                            return Err(self.report_error(
-                                name.clone(),
-                                format!("Function '{}' is private!", member_name),
-                            ));
-                        }
+                    if let Value::Function(func) = &val
+                        && !func.is_public
+                    {
+                        return Err(self.report_error(
+                            name.clone(),
+                            format!("Function '{}' is private!", member_name),
+                        ));
                     }
 
                     Ok(val)
                 } else {
                     Err(self.report_error(
                         name.clone(),
-                        "Only modules have properties!",
+                        "Only modules, maps and structs have properties!",
                     ))
                 }
             }
         }
     }
 
+    pub fn call_value(&mut self, callee: Value, args: Vec<Value>, paren: TokenSpan) -> Result<Value, Error> {
+        self.call_written(callee, args, paren, None)
+    }
+
+    // 'written' is how the callee looks in the source, when the call comes straight from it
+    fn call_written(&mut self, callee: Value, args: Vec<Value>, paren: TokenSpan, written: Option<Expr>) -> Result<Value, Error> {
+        match callee {
+            Value::NativeFn(func) => {
+                // Native functions only ever see plain intervals
+                let args = args.into_iter().map(Value::plain).collect();
+                let previous_callee = std::mem::replace(&mut self.callee, written);
+                let result = func(args, paren.clone(), self);
+                self.callee = previous_callee;
+                match result {
+                    Ok(v) => Ok(v),
+                    Err(mut e) => {
+                        if matches!(e.token.token, Token::Unknown) {
+                            e.token = paren;
+                        }
+                        Err(e)
+                    }
+                }
+            },
+            Value::Function(func) => {
+                let mut call_env = Environment::new_enclosed(func.closure.clone());
+
+                for (i, param) in func.params.iter().enumerate() {
+                    let value = if i < args.len() {
+                        args[i].clone()
+                    } else if let Some(default_expr) = &param.default {
+                        self.eval_in_env(default_expr.clone(), func.closure.clone())?
+                    } else {
+                        return Err(self.report_error(
+                            paren.clone(),
+                            format!(
+                                "Missing required argument '{}'",
+                                param.name.token_to_string()
+                            ),
+                        ));
+                    };
+
+                    call_env.define(param.name.token_to_string(), value);
+                }
+
+                if args.len() > func.params.len() {
+                    return Err(self.report_error(
+                        paren,
+                        format!(
+                            "Expected at most {} args, got {}",
+                            func.params.len(),
+                            args.len()
+                        ),
+                    ));
+                }
+
+                let previous_owner = std::mem::replace(&mut self.method_owner, func.owner.clone());
+                let previous_pending = std::mem::take(&mut self.pending_returns);
+                let value = self.execute_block(func.body.clone(), call_env);
+                self.method_owner = previous_owner;
+                let pending = std::mem::replace(&mut self.pending_returns, previous_pending);
+                // an error wins over a return that was already on its way out
+                let flow = std::mem::replace(&mut self.control_flow, ControlFlow::None);
+                let value = value?;
+                let mut result = match flow {
+                    ControlFlow::Return(returned) => returned,
+                    _ => value,
+                };
+
+                // a return taken on one side of a merged if is merged with what the function returned otherwise
+                for (condition, returned) in pending.into_iter().rev() {
+                    result = self.merge_values(returned, result, &condition)?;
+                }
+                Ok(result)
+            }
+            Value::Struct(def) => {
+                if args.len() > def.fields.len() {
+                    return Err(self.report_error(
+                        paren,
+                        format!("'{}' has {} fields, got {} values", def.name, def.fields.len(), args.len()),
+                    ));
+                }
+
+                let mut fields = Vec::new();
+                let mut args = args.into_iter();
+                for field in &def.fields {
+                    let value = match (args.next(), &field.default) {
+                        (Some(value), _) => value,
+                        (None, Some(default_expr)) => self.eval_in_env(default_expr.clone(), def.closure.clone())?,
+                        (None, None) => {
+                            return Err(self.report_error(
+                                paren.clone(),
+                                format!("Missing value for field '{}'", field.name.token_to_string()),
+                            ))
+                        }
+                    };
+                    fields.push((field.name.token_to_string(), value));
+                }
+
+                Ok(Value::Instance(Rc::new(RefCell::new(Instance { def, fields }))))
+            }
+            _ => Err(self.report_error(
+                paren,
+                format!("Value '{}' is not callable", callee),
+            )),
+        }
+    }
+
+    fn eval_in_env(&mut self, expr: Expr, env: Rc<RefCell<Environment>>) -> Result<Value, Error> {
+        let previous = std::mem::replace(&mut self.env, env);
+        let result = self.eval_expr(expr);
+        self.env = previous;
+        result
+    }
+
+    // Private members are only reachable from inside the struct's own methods
+    fn inside_methods_of(&self, def: &Rc<StructDef>) -> bool {
+        self.method_owner.as_ref().is_some_and(|owner| Rc::ptr_eq(owner, def))
+    }
+
+    fn get_member(&mut self, object: Value, name: TokenSpan) -> Result<Value, Error> {
+        let member = name.token_to_string();
+        match object {
+            Value::Instance(instance) => {
+                let def = instance.borrow().def.clone();
+
+                if let Some(field) = def.fields.iter().find(|f| f.name.token_to_string() == member) {
+                    if !field.is_public && !self.inside_methods_of(&def) {
+                        return Err(self.report_error(name, format!("Field '{}' is private!", member)));
+                    }
+                    let value = instance.borrow().fields.iter().find(|(k, _)| *k == member).map(|(_, v)| v.clone());
+                    return Ok(value.unwrap_or(Value::None));
+                }
+
+                let method = def.methods.borrow().get(&member).cloned();
+                match method {
+                    Some(method) => {
+                        if !method.is_public && !self.inside_methods_of(&def) {
+                            return Err(self.report_error(name, format!("Method '{}' is private!", member)));
+                        }
+                        Ok(Value::Function(self.bind_method(method, def, Value::Instance(instance))))
+                    }
+                    None => Err(self.report_error(
+                        name,
+                        format!("'{}' has no field or method '{}'", def.name, member),
+                    )),
+                }
+            }
+            Value::Struct(def) => {
+                let method = def.methods.borrow().get(&member).cloned();
+                match method {
+                    Some(method) if !method.is_public && !self.inside_methods_of(&def) => {
+                        Err(self.report_error(name, format!("Method '{}' is private!", member)))
+                    }
+                    Some(method) => Ok(Value::Function(Function { owner: Some(def), ..method })),
+                    None => Err(self.report_error(
+                        name,
+                        format!("'{}' has no method '{}'", def.name, member),
+                    )),
+                }
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    // Methods taking 'self' get it bound in a new scope around their closure
+    fn bind_method(&self, method: Function, def: Rc<StructDef>, instance: Value) -> Function {
+        let takes_self = method.params.first().map(|p| p.name.token_to_string() == "self").unwrap_or(false);
+        if !takes_self {
+            return Function { owner: Some(def), ..method };
+        }
+
+        let mut bound_env = Environment::new_enclosed(method.closure.clone());
+        bound_env.define("self".to_string(), instance);
+        Function {
+            params: method.params[1..].to_vec(),
+            body: method.body,
+            closure: Rc::new(RefCell::new(bound_env)),
+            is_public: method.is_public,
+            owner: Some(def),
+        }
+    }
+
+    // Element-wise operations, a single value is broadcast over the whole list
+    fn apply_list_binary(&mut self, left: Value, op: TokenSpan, right: Value) -> Result<Value, Error> {
+        match op.token {
+            Token::Plus | Token::Minus | Token::Star | Token::Slash | Token::Caret | Token::Percent => {
+                let results = match (&left, &right) {
+                    (Value::List(a), Value::List(b)) => {
+                        let (a, b) = (a.borrow().clone(), b.borrow().clone());
+                        if a.len() != b.len() {
+                            return Err(self.report_error(
+                                op,
+                                format!("List length mismatch: {} and {}", a.len(), b.len()),
+                            ));
+                        }
+                        a.into_iter()
+                            .zip(b)
+                            .map(|(l, r)| self.apply_binary(l, op.clone(), r))
+                            .collect::<Result<Vec<_>, _>>()?
+                    }
+                    (Value::List(a), scalar) => {
+                        let a = a.borrow().clone();
+                        a.into_iter()
+                            .map(|l| self.apply_binary(l, op.clone(), scalar.clone()))
+                            .collect::<Result<Vec<_>, _>>()?
+                    }
+                    (scalar, Value::List(b)) => {
+                        let b = b.borrow().clone();
+                        b.into_iter()
+                            .map(|r| self.apply_binary(scalar.clone(), op.clone(), r))
+                            .collect::<Result<Vec<_>, _>>()?
+                    }
+                    _ => unreachable!(),
+                };
+                Ok(Value::list(results))
+            }
+            Token::EqualEqual | Token::BangEqual => {
+                let equal = match (&left, &right) {
+                    (Value::List(a), Value::List(b)) if a.borrow().len() == b.borrow().len() => {
+                        let (a, b) = (a.borrow().clone(), b.borrow().clone());
+                        let mut all = SKBool::True;
+                        for (l, r) in a.into_iter().zip(b) {
+                            let eq_span = TokenSpan { token: Token::EqualEqual, ..op.clone() };
+                            match self.apply_binary(l, eq_span, r)? {
+                                Value::Bool(b) => all = logic::and(all, b),
+                                _ => all = logic::and(all, SKBool::Partial),
+                            }
+                        }
+                        all
+                    }
+                    _ => SKBool::False,
+                };
+                if op.token == Token::EqualEqual {
+                    Ok(Value::Bool(equal))
+                } else {
+                    Ok(Value::Bool(logic::not(equal)))
+                }
+            }
+            _ => Err(self.report_error(op, "Invalid operation on lists")),
+        }
+    }
+
+    // Every combination of the options, if there are too many we settle for the interval around them
+    fn apply_one_of_binary(&mut self, left: Value, op: TokenSpan, right: Value) -> Result<Value, Error> {
+        let options = |value: &Value| match value {
+            Value::OneOf(options) => options.clone(),
+            other => vec![other.clone()],
+        };
+        let (lefts, rights) = (options(&left), options(&right));
+
+        if lefts.len() * rights.len() > ONE_OF_LIMIT {
+            return match (left.hull(), right.hull()) {
+                (Some(l), Some(r)) => self.apply_binary(l, op, r),
+                _ => Ok(Value::Unknown),
+            };
+        }
+
+        let mut results = Vec::new();
+        for l in &lefts {
+            for r in &rights {
+                results.push(self.apply_binary(l.clone(), op.clone(), r.clone())?);
+            }
+        }
+        Ok(Value::one_of(results))
+    }
+
     fn apply_binary(&mut self, left: Value, op: TokenSpan, right: Value) -> Result<Value, Error> {
         let operator = op.token.clone();
 
+        if matches!(left, Value::List(_)) || matches!(right, Value::List(_)) {
+            return self.apply_list_binary(left, op, right);
+        }
+
         match operator { // Pre-calculations for x - x, x / x and x * 0
             Token::Star => {
-                if let Value::Number(n) = left { if n == 0.0 { return Ok(Value::Number(0.0)); } }
-                if let Value::Number(n) = right { if n == 0.0 { return Ok(Value::Number(0.0)); } }
+                if let Value::Number(n) = left && n == 0.0 { return Ok(Value::Number(0.0)); }
+                if let Value::Number(n) = right && n == 0.0 { return Ok(Value::Number(0.0)); }
             }
-            Token::Minus => {
-                if left == right { return Ok(Value::Number(0.0)); }
-            }
-            Token::Slash => {
-                if left == right {
-                    match left {
-                        Value::Number(n) if n != 0.0 => return Ok(Value::Number(1.0)),
-                        Value::Unknown | Value::Symbolic { .. } => return Ok(Value::Number(1.0)),
-                        _ => {} 
+            Token::Minus if left == right => return Ok(Value::Number(0.0)),
+            Token::Slash if left == right => {
+                match left {
+                    Value::Number(n) if n != 0.0 => return Ok(Value::Number(1.0)),
+                    Value::Unknown | Value::Symbolic { .. } => return Ok(Value::Number(1.0)),
+                    Value::Bounded(ref b) if b.reciprocal().is_some() => return Ok(Value::Number(1.0)),
+                    // the same one of on both sides is the same option, {1, 2} / itself is 1
+                    Value::OneOf(ref options) if options.iter().all(|o| matches!(o, Value::Number(n) if *n != 0.0)) => {
+                        return Ok(Value::Number(1.0));
                     }
+                    _ => {} 
                 }
             }
             _ => {}
         }
 
+        if (matches!(left, Value::OneOf(_)) || matches!(right, Value::OneOf(_)))
+            && matches!(operator, Token::Plus | Token::Minus | Token::Star | Token::Slash | Token::Caret | Token::Percent)
+        {
+            return self.apply_one_of_binary(left, op, right);
+        }
+
+        if operator == Token::Slash
+            && let Value::Bounded(b) = &right
+            && let Some(reciprocal) = b.reciprocal()
+        {
+            let left = left.widen();
+            return match left.mul(&reciprocal) {
+                Ok(val) => {
+                    self.note_binary(&op, &left, &right, &val);
+                    Ok(val)
+                }
+                Err(_) if left.is_symbolic_or_unknown() => self.propagate_symbolic(left, op, right),
+                Err(e) => Err(self.report_error(op, e.message)),
+            };
+        }
+
+        let (left, right) = match operator {
+            Token::Plus | Token::Minus | Token::Star | Token::Slash | Token::Caret => (left.widen(), right.widen()),
+            _ => (left, right),
+        };
+
         if left == Value::Unknown || right == Value::Unknown {
-            return Ok(Value::Unknown);
+            // comparing with an unknown could go either way, solve() is what treats it as an equation
+            let comparison = matches!(
+                operator,
+                Token::EqualEqual | Token::BangEqual | Token::Greater | Token::GreaterEqual | Token::Less | Token::LessEqual
+            );
+            return Ok(if comparison { Value::Bool(SKBool::Partial) } else { Value::Unknown });
         }
 
         let is_symbolic = left.is_symbolic_or_unknown() || right.is_symbolic_or_unknown();
+        if operator == Token::Caret && left.power_clips(&right) {
+            self.warn(op.clone(), "Fractional powers are only defined for numbers that aren't negative, the negative part of the interval is ignored");
+        }
 
         let res: Result<Value, String> = match operator {
             Token::Plus => left.add(&right).map_err(|e| e.message),
@@ -689,6 +1858,7 @@ impl Evaluator {
             Token::Star => left.mul(&right).map_err(|e| e.message),
             Token::Slash => left.div(&right).map_err(|e| e.message),
             Token::Caret => left.pow(&right).map_err(|e| e.message),
+            Token::Percent => left.rem(&right).map_err(|e| e.message),
 
             Token::EqualEqual
             | Token::BangEqual
@@ -703,56 +1873,15 @@ impl Evaluator {
         };
 
         match res {
-            Ok(val) => Ok(val),
+            Ok(val) => {
+                self.note_binary(&op, &left, &right, &val);
+                Ok(val)
+            }
             Err(_) if is_symbolic => self.propagate_symbolic(left, op, right),
             Err(msg) => Err(self.report_error(op, msg)),
         }
     }
 
-    fn simplify_symbolic(expr: Expr) -> Expr {
-        match expr {
-            Expr::Binary { left, operator, right } => {
-                let left = Self::simplify_symbolic(*left);
-                let right = Self::simplify_symbolic(*right);
-
-                match (&left, &operator.token, &right) {
-                    (Expr::Literal { value: l_val }, Token::Plus, Expr::Literal { value: r_val }) => {
-                        if let (Token::Number(a), Token::Number(b)) = (&l_val.token, &r_val.token) {
-                            return Expr::Literal {
-                                value: TokenSpan {
-                                    token: Token::Number(a + b),
-                                    ..operator.clone()
-                                }
-                            };
-                        }
-                    }
-                    // x + 0 = x ; x - 0 = x
-                    (Expr::Literal { value: val }, Token::Plus, _) | (_, Token::Plus, Expr::Literal { value: val }) => {
-                        if let Token::Number(0.0) = val.token {
-                            return if let Token::Number(0.0) = val.token { 
-                                if matches!(left, Expr::Literal { .. }) { right } else { left } 
-                            } else { 
-                                Expr::Binary { left: Box::new(left), operator, right: Box::new(right) }
-                            };
-                        }
-                    }
-                    // 0 * x = 0 ; 1 * x = x
-                    (Expr::Literal { value: val }, Token::Star, _) | (_, Token::Star, Expr::Literal { value: val }) => {
-                        if let Token::Number(0.0) = val.token {
-                            return Expr::Literal { value: val.clone() };
-                        }
-                        if let Token::Number(1.0) = val.token {
-                            return if matches!(left, Expr::Literal { .. }) { right } else { left };
-                        }
-                    }
-                    _ => {}
-                }
-                Expr::Binary { left: Box::new(left), operator, right: Box::new(right) }
-            }
-            _ => expr,
-        }
-    }
-
     // Now it should properly handle symbolic values extracting the inner expression
     fn propagate_symbolic(
         &self,
@@ -766,29 +1895,10 @@ impl Evaluator {
             _ => false,
         };
 
-        let left_expr = match left {
-            Value::Symbolic { expression, .. } => *expression,
-            _ => Expr::Literal {
-                value: TokenSpan {
-                    token: self.value_to_token(left),
-                    line: 0,
-                    column: 0,
-                },
-            },
-        };
-
-        let right_expr = match right {
-            Value::Symbolic { expression, .. } => *expression,
-            _ => Expr::Literal {
-                value: TokenSpan {
-                    token: self.value_to_token(right),
-                    line: 0,
-                    column: 0,
-                },
-            },
-        };
+        let left_expr = self.value_to_expr(left);
+        let right_expr = self.value_to_expr(right);
 
-        let expression = Self::simplify_symbolic(Expr::Binary {
+        let expression = algebra::simplify(&Expr::Binary {
             left: Box::new(left_expr),
             operator: op,
             right: Box::new(right_expr),
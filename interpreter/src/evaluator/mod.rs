@@ -1,3 +1,4 @@
 pub mod env;
 pub mod eval;
-pub mod builtins;
\ No newline at end of file
+pub mod builtins;
+pub mod trace;
\ No newline at end of file
@@ -0,0 +1,60 @@
+use crate::parser::lexer::TokenSpan;
+
+// Where the uncertainty of a value came from, used by explain()
+// There is at most one event per place in the source, so loops don't pile them up
+// A place is the file and the position in it, an imported file has the same lines as the one importing it
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Trace {
+    events: Vec<Event>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Event {
+    file: Option<String>,
+    line: usize,
+    column: usize,
+    message: String,
+}
+
+impl Trace {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+
+    // A later event at the same place replaces the older one
+    pub fn note(&mut self, file: Option<String>, at: &TokenSpan, message: impl Into<String>) {
+        let event = Event {
+            file,
+            line: at.line,
+            column: at.column,
+            message: message.into(),
+        };
+        self.events.retain(|e| !e.same_place(&event));
+        self.events.push(event);
+    }
+
+    pub fn join(&mut self, other: Trace) {
+        for event in other.events {
+            if !self.events.iter().any(|e| e.same_place(&event)) {
+                self.events.push(event);
+            }
+        }
+    }
+
+    pub fn lines(&self) -> Vec<String> {
+        self.events
+            .iter()
+            .map(|e| format!("line {}: {}", e.line, e.message))
+            .collect()
+    }
+}
+
+impl Event {
+    fn same_place(&self, other: &Event) -> bool {
+        (&self.file, self.line, self.column) == (&other.file, other.line, other.column)
+    }
+}
@@ -1,3 +1,5 @@
+#![allow(clippy::result_large_err)]
+
 use std::path::Path;
 use std::fs;
 use std::rc::Rc;
@@ -14,10 +16,18 @@ use crate::evaluator::eval::Evaluator;
 use crate::evaluator::env::Environment;
 use crate::core::value::Value;
 use crate::core::error::{Error, ErrorReporter, Warning};
+use crate::core::settings::{self, Settings};
 
 pub struct SKInterpreter {
     env: Rc<RefCell<Environment>>,
     reporter: Rc<RefCell<ErrorReporter>>,
+    settings: Settings,
+}
+
+impl Default for SKInterpreter {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl SKInterpreter {
@@ -25,9 +35,38 @@ impl SKInterpreter {
         Self {
             env: Rc::new(RefCell::new(Environment::new())),
             reporter: Rc::new(RefCell::new(ErrorReporter::new())),
+            settings: Settings::default(),
         }
     }
 
+    // Dividing by an interval that contains zero errors instead of splitting the result
+    pub fn set_strict_division(&mut self, strict: bool) {
+        self.settings.strict_division = strict;
+    }
+
+    // Interval results are rounded outward so they are guaranteed to hold the exact answer
+    pub fn set_outward_rounding(&mut self, outward: bool) {
+        self.settings.outward_rounding = outward;
+    }
+
+    // Interval literals become affine forms, so uncertainty that comes from the same place can cancel out
+    pub fn set_affine_arithmetic(&mut self, affine: bool) {
+        self.settings.affine = affine;
+    }
+
+    // How many samples every distribution from the dist library is drawn with
+    pub fn set_sample_count(&mut self, samples: usize) {
+        self.settings.samples = samples.max(1);
+    }
+
+    // What explain() prints for a variable of the global scope
+    pub fn explain(&mut self, name: &str) -> Option<String> {
+        let value = self.env.borrow().get(name).ok()?;
+        let trace = self.env.borrow().trace(name);
+        let evaluator = Evaluator::new(self.env.clone(), self.reporter.clone());
+        Some(crate::evaluator::builtins::explanation(&value, &trace, &evaluator))
+    }
+
     pub fn take_warnings(&mut self) -> Vec<Warning> {
         self.reporter.borrow_mut().take_warnings()
     }
@@ -52,6 +91,7 @@ impl SKInterpreter {
 
     fn execute_named(&mut self, name: String, source: String) -> Result<Value, Error> {
         let previous = self.reporter.borrow_mut().set_source(name, source.clone());
+        let previous_settings = settings::replace(self.settings);
 
         let result = (|| {
             let mut lexer = Lexer::new(source, self.reporter.clone());
@@ -65,6 +105,7 @@ impl SKInterpreter {
         })();
 
         self.reporter.borrow_mut().restore_source(previous);
+        settings::replace(previous_settings);
 
         result
     }
@@ -0,0 +1,126 @@
+use crate::evaluator::env::Environment;
+use crate::evaluator::eval::Evaluator;
+use crate::parser::lexer::TokenSpan;
+use crate::core::error::Error;
+use crate::core::dist::Dist;
+use crate::core::value::{SKBool, Value};
+
+// Probability distributions, the other way of saying how uncertain a value is
+
+pub fn register(env: &mut Environment) {
+    env.define("uniform".into(), Value::NativeFn(uniform));
+    env.define("normal".into(), Value::NativeFn(normal));
+    env.define("triangular".into(), Value::NativeFn(triangular));
+    env.define("discrete".into(), Value::NativeFn(discrete));
+
+    env.define("mean".into(), Value::NativeFn(mean));
+    env.define("std".into(), Value::NativeFn(std));
+    env.define("quantile".into(), Value::NativeFn(quantile));
+    env.define("toInterval".into(), Value::NativeFn(to_interval));
+    env.define("probability".into(), Value::NativeFn(probability));
+}
+
+fn numbers(args: &[Value]) -> Option<Vec<f64>> {
+    args.iter()
+        .map(|arg| match arg {
+            Value::Number(n) => Some(*n),
+            _ => None,
+        })
+        .collect()
+}
+
+// Works both with 2 numbers or one interval
+pub fn uniform(args: Vec<Value>, span: TokenSpan, eval: &mut Evaluator) -> Result<Value, Error> {
+    let (min, max) = match (args.first(), numbers(&args).as_deref()) {
+        (Some(Value::Interval(min, max)), _) if args.len() == 1 => (*min, *max),
+        (_, Some([min, max])) => (*min, *max),
+        _ => return Err(eval.error(span, "uniform() expects an interval or 2 numbers")),
+    };
+    if min > max || !(max - min).is_finite() {
+        return Err(eval.error(span, "uniform() needs a finite range with min <= max"));
+    }
+    Ok(Value::Dist(Dist::uniform(min, max)))
+}
+
+pub fn normal(args: Vec<Value>, span: TokenSpan, eval: &mut Evaluator) -> Result<Value, Error> {
+    match numbers(&args).as_deref() {
+        Some([mean, std]) if *std >= 0.0 => Ok(Value::Dist(Dist::normal(*mean, *std))),
+        Some([_, _]) => Err(eval.error(span, "normal() needs a standard deviation that isn't negative")),
+        _ => Err(eval.error(span, "normal() expects a mean and a standard deviation")),
+    }
+}
+
+pub fn triangular(args: Vec<Value>, span: TokenSpan, eval: &mut Evaluator) -> Result<Value, Error> {
+    match numbers(&args).as_deref() {
+        Some([min, mode, max]) if min <= mode && mode <= max && min < max => {
+            Ok(Value::Dist(Dist::triangular(*min, *mode, *max)))
+        }
+        Some([_, _, _]) => Err(eval.error(span, "triangular() needs min <= mode <= max")),
+        _ => Err(eval.error(span, "triangular() expects a min, a mode and a max")),
+    }
+}
+
+// discrete([1, 2, 3]) has every value equally likely, a second list gives their weights
+pub fn discrete(args: Vec<Value>, span: TokenSpan, eval: &mut Evaluator) -> Result<Value, Error> {
+    let list = |arg: Option<&Value>| match arg {
+        Some(Value::List(items)) => numbers(&items.borrow()),
+        Some(Value::OneOf(options)) => numbers(options),
+        _ => None,
+    };
+
+    let Some(values) = list(args.first()).filter(|v| !v.is_empty()) else {
+        return Err(eval.error(span, "discrete() expects a list of numbers"));
+    };
+    let weights = match args.get(1) {
+        None => vec![1.0; values.len()],
+        other => match list(other) {
+            Some(weights) if weights.len() == values.len() && weights.iter().all(|w| *w >= 0.0) && weights.iter().sum::<f64>() > 0.0 => weights,
+            _ => return Err(eval.error(span, "discrete() weights must be a list of positive numbers, one for each value")),
+        },
+    };
+    Ok(Value::Dist(Dist::discrete(values.into_iter().zip(weights).collect())))
+}
+
+pub fn mean(args: Vec<Value>, span: TokenSpan, eval: &mut Evaluator) -> Result<Value, Error> {
+    match args.first() {
+        Some(Value::Dist(d)) => Ok(Value::Number(d.mean())),
+        Some(Value::Number(n)) => Ok(Value::Number(*n)),
+        _ => Err(eval.error(span, "mean() expects 1 distribution")),
+    }
+}
+
+pub fn std(args: Vec<Value>, span: TokenSpan, eval: &mut Evaluator) -> Result<Value, Error> {
+    match args.first() {
+        Some(Value::Dist(d)) => Ok(Value::Number(d.std())),
+        Some(Value::Number(_)) => Ok(Value::Number(0.0)),
+        _ => Err(eval.error(span, "std() expects 1 distribution")),
+    }
+}
+
+pub fn quantile(args: Vec<Value>, span: TokenSpan, eval: &mut Evaluator) -> Result<Value, Error> {
+    match (args.first(), args.get(1)) {
+        (Some(Value::Dist(d)), Some(Value::Number(p))) if (0.0..=1.0).contains(p) => Ok(Value::Number(d.quantile(*p))),
+        _ => Err(eval.error(span, "quantile() expects a distribution and a number between 0 and 1")),
+    }
+}
+
+// The central interval that holds a fraction p of the distribution
+pub fn to_interval(args: Vec<Value>, span: TokenSpan, eval: &mut Evaluator) -> Result<Value, Error> {
+    match (args.first(), args.get(1)) {
+        (Some(Value::Dist(d)), Some(Value::Number(p))) if (0.0..=1.0).contains(p) => {
+            Ok(Value::Interval(d.quantile((1.0 - p) / 2.0), d.quantile((1.0 + p) / 2.0)))
+        }
+        _ => Err(eval.error(span, "toInterval() expects a distribution and a number between 0 and 1")),
+    }
+}
+
+// How likely a condition is, none when there is no estimate for it
+pub fn probability(args: Vec<Value>, span: TokenSpan, eval: &mut Evaluator) -> Result<Value, Error> {
+    match args.first() {
+        Some(Value::Bool(SKBool::True)) => Ok(Value::Number(1.0)),
+        Some(Value::Bool(SKBool::False)) => Ok(Value::Number(0.0)),
+        Some(Value::Bool(SKBool::Weighted(p, _))) => Ok(Value::Number(*p)),
+        Some(Value::Bool(SKBool::Partial)) => Ok(Value::None),
+        _ => Err(eval.error(span, "probability() expects 1 boolean")),
+    }
+}
@@ -3,6 +3,7 @@ use crate::evaluator::env::Environment;
 use crate::evaluator::eval::Evaluator;
 use crate::core::error::Error;
 use crate::parser::lexer::TokenSpan;
+use std::f64::consts::{FRAC_PI_2, PI, TAU};
 
 // The SK Math library!
 
@@ -37,74 +38,144 @@ pub fn register(env: &mut Environment) {
     env.define("mid".into(), Value::NativeFn(mid));
     env.define("intersection".into(), Value::NativeFn(intersection));
     env.define("union".into(), Value::NativeFn(union));
+    env.define("complement".into(), Value::NativeFn(complement));
+    env.define("contains".into(), Value::NativeFn(contains));
 }
 
 pub fn sqrt(args: Vec<Value>, span: TokenSpan, eval: &mut Evaluator) -> Result<Value, Error> {
-    match args.first() {
-        Some(Value::Number(n)) => Ok(Value::Number(n.sqrt())),
-        Some(Value::Interval(min, max)) => {
-            if *min < 0.0 {
-                return Err(eval.error(span, "Cannot take sqrt of negative interval"));
-            }
-            Ok(Value::Interval(min.sqrt(), max.sqrt()))
+    warn_clipped("sqrt", &args, &span, eval);
+    extend("sqrt", args, span, eval, f64::sqrt, |min, max| {
+        if max < 0.0 {
+            return Err("sqrt() is not defined for negative numbers".to_string());
         }
-        _ => Err(eval.error(span, "sqrt() expects 1 number or interval")),
-    }
+        Ok(Value::Interval(min.max(0.0).sqrt(), max.sqrt()))
+    })
 }
 
 pub fn sin(args: Vec<Value>, span: TokenSpan, eval: &mut Evaluator) -> Result<Value, Error> {
-    match args.first() {
-        Some(Value::Number(n)) => Ok(Value::Number(n.sin())),
-        _ => Err(eval.error(span, "sin() expects 1 number")),
-    }
+    extend("sin", args, span, eval, f64::sin, |min, max| Ok(periodic(min, max, f64::sin, FRAC_PI_2, -FRAC_PI_2)))
 }
 
 pub fn cos(args: Vec<Value>, span: TokenSpan, eval: &mut Evaluator) -> Result<Value, Error> {
-    match args.first() {
-        Some(Value::Number(n)) => Ok(Value::Number(n.cos())),
-        _ => Err(eval.error(span, "cos() expects 1 number")),
-    }
+    extend("cos", args, span, eval, f64::cos, |min, max| Ok(periodic(min, max, f64::cos, 0.0, PI)))
 }
 
 pub fn tan(args: Vec<Value>, span: TokenSpan, eval: &mut Evaluator) -> Result<Value, Error> {
-    match args.first() {
-        Some(Value::Number(n)) => Ok(Value::Number(n.tan())),
-        _ => Err(eval.error(span, "tan() expects 1 number")),
-    }
+    extend("tan", args, span, eval, f64::tan, |min, max| {
+        // tan only goes up between its poles, crossing one wraps the result around infinity
+        let pole = FRAC_PI_2 + ((min - FRAC_PI_2) / PI).ceil() * PI;
+        if pole > max {
+            Ok(Value::Interval(min.tan(), max.tan()))
+        } else if pole + PI <= max {
+            Ok(Value::Interval(f64::NEG_INFINITY, f64::INFINITY))
+        } else {
+            Ok(Value::from_pieces(vec![(f64::NEG_INFINITY, max.tan()), (min.tan(), f64::INFINITY)]))
+        }
+    })
 }
 
 pub fn log10(args: Vec<Value>, span: TokenSpan, eval: &mut Evaluator) -> Result<Value, Error> {
-    match args.first() {
-        Some(Value::Number(n)) => Ok(Value::Number(n.log10())),
-        _ => Err(eval.error(span, "log10() expects 1 number")),
-    }
+    warn_clipped("log10", &args, &span, eval);
+    extend("log10", args, span, eval, f64::log10, |min, max| logarithm("log10", min, max, f64::log10))
 }
 
 pub fn log2(args: Vec<Value>, span: TokenSpan, eval: &mut Evaluator) -> Result<Value, Error> {
-    match args.first() {
-        Some(Value::Number(n)) => Ok(Value::Number(n.log2())),
-        _ => Err(eval.error(span, "log2() expects 1 number")),
-    }
+    warn_clipped("log2", &args, &span, eval);
+    extend("log2", args, span, eval, f64::log2, |min, max| logarithm("log2", min, max, f64::log2))
 }
 
 pub fn ln(args: Vec<Value>, span: TokenSpan, eval: &mut Evaluator) -> Result<Value, Error> {
-    match args.first() {
-        Some(Value::Number(n)) => Ok(Value::Number(n.ln())),
-        _ => Err(eval.error(span, "ln() expects 1 number")),
-    }
+    warn_clipped("ln", &args, &span, eval);
+    extend("ln", args, span, eval, f64::ln, |min, max| logarithm("ln", min, max, f64::ln))
 }
 
 pub fn exp(args: Vec<Value>, span: TokenSpan, eval: &mut Evaluator) -> Result<Value, Error> {
-    match args.first() {
-        Some(Value::Number(n)) => Ok(Value::Number(n.exp())),
-        _ => Err(eval.error(span, "exp() expects 1 number")),
-    }
+    extend("exp", args, span, eval, f64::exp, |min, max| Ok(Value::Interval(min.exp(), max.exp())))
 }
 
 pub fn abs(args: Vec<Value>, span: TokenSpan, eval: &mut Evaluator) -> Result<Value, Error> {
-    match args.first() {
-        Some(Value::Number(n)) => Ok(Value::Number(n.abs())),
-        _ => Err(eval.error(span, "abs() expects 1 number")),
+    extend("abs", args, span, eval, f64::abs, |min, max| {
+        Ok(if min >= 0.0 {
+            Value::Interval(min, max)
+        } else if max <= 0.0 {
+            Value::Interval(-max, -min)
+        } else {
+            Value::Interval(0.0, max.max(-min))
+        })
+    })
+}
+
+// Runs a one argument function over numbers and everything built from intervals,
+// 'interval' gets the bounds of each piece and has to give back everything the function reaches on it
+fn extend(
+    name: &str,
+    args: Vec<Value>,
+    span: TokenSpan,
+    eval: &mut Evaluator,
+    number: fn(f64) -> f64,
+    interval: fn(f64, f64) -> Result<Value, String>,
+) -> Result<Value, Error> {
+    let Some(arg) = args.into_iter().next() else {
+        return Err(eval.error(span, format!("{}() expects 1 number or interval", name)));
+    };
+
+    match arg.widen() {
+        Value::Number(n) => Ok(Value::Number(number(n))),
+        Value::Interval(min, max) => match interval(min, max) {
+            Ok(result) => Ok(result.outward()),
+            Err(msg) => Err(eval.error(span, msg)),
+        },
+        Value::Union(pieces) => {
+            let mut results = Vec::new();
+            for (min, max) in pieces {
+                match interval(min, max) {
+                    Ok(result) => results.extend(result.pieces().unwrap_or_default()),
+                    Err(msg) => return Err(eval.error(span, msg)),
+                }
+            }
+            Ok(Value::from_pieces(results).outward())
+        }
+        Value::OneOf(options) => {
+            let mut results = Vec::new();
+            for option in options {
+                results.push(extend(name, vec![option], span.clone(), eval, number, interval)?);
+            }
+            Ok(Value::one_of(results))
+        }
+        Value::Empty => Ok(Value::Empty),
+        value @ (Value::Unknown | Value::Symbolic { .. }) => {
+            Ok(eval.symbolic_call(&format!("math.{}", name), span, vec![value]))
+        }
+        _ => Err(eval.error(span, format!("{}() expects 1 number or interval", name))),
+    }
+}
+
+// sin and cos, the ends only matter when no peak or trough falls in between
+fn periodic(min: f64, max: f64, f: fn(f64) -> f64, peak: f64, trough: f64) -> Value {
+    if max - min >= TAU {
+        return Value::Interval(-1.0, 1.0);
+    }
+    let reaches = |at: f64| at + ((min - at) / TAU).ceil() * TAU <= max;
+    let (a, b) = (f(min), f(max));
+    let low = if reaches(trough) { -1.0 } else { a.min(b) };
+    let high = if reaches(peak) { 1.0 } else { a.max(b) };
+    Value::Interval(low, high)
+}
+
+// Logarithms only see the positive part of the interval
+fn logarithm(name: &str, min: f64, max: f64, f: fn(f64) -> f64) -> Result<Value, String> {
+    if max <= 0.0 {
+        return Err(format!("{}() is not defined for numbers below or at 0", name));
+    }
+    Ok(Value::Interval(f(min.max(0.0)), f(max)))
+}
+
+fn warn_clipped(name: &str, args: &[Value], span: &TokenSpan, eval: &mut Evaluator) {
+    if let Some(Value::Interval(min, max)) = args.first().and_then(Value::hull)
+        && min < 0.0
+        && max > 0.0
+    {
+        eval.warn(span.clone(), format!("{}() is not defined for negative numbers, the negative part of the interval is ignored", name));
     }
 }
 
@@ -113,7 +184,7 @@ pub fn min(args: Vec<Value>, span: TokenSpan, eval: &mut Evaluator) -> Result<Va
         return Err(eval.error(span, "min() expects at least 2 numbers"));
     }
 
-    let mut min_val = std::f64::INFINITY;
+    let mut min_val = f64::INFINITY;
     for arg in args {
         match arg {
             Value::Number(n) => {
@@ -132,7 +203,7 @@ pub fn max(args: Vec<Value>, span: TokenSpan, eval: &mut Evaluator) -> Result<Va
         return Err(eval.error(span, "max() expects at least 2 numbers"));
     }
 
-    let mut max_val = std::f64::NEG_INFINITY;
+    let mut max_val = f64::NEG_INFINITY;
     for arg in args {
         match arg {
             Value::Number(n) => {
@@ -205,17 +276,9 @@ pub fn intersection(args: Vec<Value>, span: TokenSpan, eval: &mut Evaluator) ->
         return Err(eval.error(span, "intersection() expects exactly 2 intervals"));
     }
 
-    match (&args[0], &args[1]) {
-        (Value::Interval(min1, max1), Value::Interval(min2, max2)) => {
-            let new_min = min1.max(*min2);
-            let new_max = max1.min(*max2);
-            if new_min > new_max {
-                Ok(Value::Interval(0.0, 0.0)) // No intersection
-            } else {
-                Ok(Value::Interval(new_min, new_max))
-            }
-        }
-        _ => Err(eval.error(span, "intersection() expects only intervals")),
+    match args[0].intersection(&args[1]) {
+        Some(result) => Ok(result),
+        None => Err(eval.error(span, "intersection() expects only intervals")),
     }
 }
 
@@ -224,12 +287,26 @@ pub fn union(args: Vec<Value>, span: TokenSpan, eval: &mut Evaluator) -> Result<
         return Err(eval.error(span, "union() expects exactly 2 intervals"));
     }
 
-    match (&args[0], &args[1]) {
-        (Value::Interval(min1, max1), Value::Interval(min2, max2)) => {
-            let new_min = min1.min(*min2);
-            let new_max = max1.max(*max2);
-            Ok(Value::Interval(new_min, new_max))
-        }
-        _ => Err(eval.error(span, "union() expects only intervals")),
+    match args[0].union(&args[1]) {
+        Some(result) => Ok(result),
+        None => Err(eval.error(span, "union() expects only intervals")),
+    }
+}
+
+pub fn complement(args: Vec<Value>, span: TokenSpan, eval: &mut Evaluator) -> Result<Value, Error> {
+    match args.first().and_then(Value::complement) {
+        Some(result) => Ok(result),
+        None => Err(eval.error(span, "complement() expects 1 interval")),
+    }
+}
+
+pub fn contains(args: Vec<Value>, span: TokenSpan, eval: &mut Evaluator) -> Result<Value, Error> {
+    if args.len() != 2 {
+        return Err(eval.error(span, "contains() expects an interval and a value"));
+    }
+
+    match args[0].contains(&args[1]) {
+        Some(result) => Ok(Value::Bool(result)),
+        None => Err(eval.error(span, "contains() expects an interval and a number or interval")),
     }
 }
\ No newline at end of file
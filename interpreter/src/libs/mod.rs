@@ -3,6 +3,7 @@ pub mod os;
 pub mod fs;
 pub mod rand;
 pub mod time;
+pub mod dist;
 
 use crate::evaluator::env::Environment;
 use std::collections::HashMap;
@@ -19,6 +20,7 @@ pub fn get_library_registry() -> HashMap<String, LibRegisterFn> {
     registry.insert("fs".to_string(), crate::libs::fs::register);
     registry.insert("rand".to_string(), crate::libs::rand::register);
     registry.insert("time".to_string(), crate::libs::time::register);
+    registry.insert("dist".to_string(), crate::libs::dist::register);
     
     registry
 }
\ No newline at end of file
@@ -11,10 +11,8 @@ const VERSION: &str = env!("CARGO_PKG_VERSION");
 use sk_lang::SKInterpreter;
 use sk_lang::core::value::Value;
 
-fn run(path: &Path) {
-    let mut interpreter = SKInterpreter::new();
-
-    match interpreter.execute(&path) {
+fn run(path: &Path, mut interpreter: SKInterpreter) {
+    match interpreter.execute(path) {
         Ok(value) => {
             if value != Value::None {
                 println!("{}", value);
@@ -30,8 +28,7 @@ fn run(path: &Path) {
     }
 }
 
-fn run_repl() {
-    let mut interpreter = SKInterpreter::new();
+fn run_repl(mut interpreter: SKInterpreter) {
     let mut rl = DefaultEditor::new().expect("Failed to create editor");
     
     println!("{} REPL ({}). Type 'exit' to quit.", NAME, VERSION);
@@ -98,15 +95,43 @@ fn create_proj(name: String) {
     path.push("main.sk");
 
     std::fs::write(&path, 
-        &format!("// SK Version: {}\n\nprint('Hello, World!')", VERSION)
+        format!("// SK Version: {}\n\nprint('Hello, World!')", VERSION)
     ).expect("Failed to create main.sk");
 }
 
+// Removes an option flag from the arguments, returns whether it was there
+fn take_flag(args: &mut Vec<String>, flag: &str) -> bool {
+    let before = args.len();
+    args.retain(|arg| arg != flag);
+    args.len() != before
+}
+
+// Removes an option and the value after it, e.g. '--samples 1000'
+fn take_option(args: &mut Vec<String>, option: &str) -> Option<String> {
+    let index = args.iter().position(|arg| arg == option)?;
+    args.remove(index);
+    (index < args.len()).then(|| args.remove(index))
+}
+
 fn main() {
-    let args: Vec<String> = env::args().skip(1).collect();
+    let mut args: Vec<String> = env::args().skip(1).collect();
+
+    let mut interpreter = SKInterpreter::new();
+    interpreter.set_strict_division(take_flag(&mut args, "--strict-division"));
+    interpreter.set_outward_rounding(take_flag(&mut args, "--outward-rounding"));
+    interpreter.set_affine_arithmetic(take_flag(&mut args, "--affine"));
+    if let Some(samples) = take_option(&mut args, "--samples") {
+        match samples.parse() {
+            Ok(samples) if samples > 0 => interpreter.set_sample_count(samples),
+            _ => {
+                eprintln!("--samples expects a positive whole number, got '{}'", samples);
+                process::exit(1);
+            }
+        }
+    }
 
     if args.is_empty() {
-        run_repl();
+        run_repl(interpreter);
         return;
     }
 
@@ -149,7 +174,7 @@ fn main() {
         process::exit(1)
     }
 
-    run(&path);
+    run(&path, interpreter);
 }
 
 fn help() {
@@ -160,4 +185,10 @@ fn help() {
     println!("       {} --project new : creates a new project.", NAME);
     println!("       {} --version : shows interpreter's version.", NAME);
     println!("       {} --help : shows this dialog.", NAME);
+    println!();
+    println!("options:");
+    println!("       --strict-division : dividing by an interval containing zero is an error.");
+    println!("       --outward-rounding : interval bounds are rounded outward to always hold the exact result.");
+    println!("       --affine : intervals remember where their uncertainty came from, so it can cancel out.");
+    println!("       --samples <n> : how many samples distributions are drawn with, 10000 by default.");
 }
\ No newline at end of file
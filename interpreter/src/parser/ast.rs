@@ -35,21 +35,60 @@ pub enum Expr {
         statements: Vec<Stmt> 
     },
 
+    Lambda {
+        params: Vec<Parameter>,
+        body: Vec<Stmt>,
+    },
+
     Call {
         callee: Box<Expr>,
         paren: TokenSpan, 
         arguments: Vec<Expr>,
     },
 
+    List {
+        elements: Vec<Expr>,
+        bracket: TokenSpan,
+    },
+
+    Index {
+        object: Box<Expr>,
+        index: Box<Expr>,
+        bracket: TokenSpan,
+    },
+
+    Map {
+        entries: Vec<(TokenSpan, Expr)>,
+        brace: TokenSpan,
+    },
+
+    OneOf {
+        options: Vec<Expr>,
+        brace: TokenSpan,
+    },
+
     Get {
         object: Box<Expr>,
         name: TokenSpan,
     },
 
+    Set {
+        object: Box<Expr>,
+        name: TokenSpan,
+        value: Box<Expr>,
+    },
+
     Postfix {
         name: TokenSpan,
         operator: TokenSpan,
-    }
+    },
+
+    // Only built by merging symbolic branches, there is no syntax for it
+    Conditional {
+        condition: Box<Expr>,
+        then_branch: Box<Expr>,
+        else_branch: Box<Expr>,
+    },
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -62,6 +101,7 @@ pub enum Stmt {
     Let {
         name: TokenSpan,
         initializer: Expr,
+        is_const: bool,
     },
 
     Assign {
@@ -73,6 +113,13 @@ pub enum Stmt {
         name: TokenSpan,
         initializer: Expr,
         is_quiet: bool,
+        is_const: bool,
+    },
+
+    // unknown with a constraint, a plain 'unknown x' is just a Let
+    Unknown {
+        name: TokenSpan,
+        constraint: Expr,
     },
 
     Print {
@@ -104,11 +151,38 @@ pub enum Stmt {
         is_public: bool
     },
 
+    Struct {
+        name: TokenSpan,
+        fields: Vec<Field>,
+    },
+
+    Impl {
+        name: TokenSpan,
+        methods: Vec<Stmt>,
+    },
+
     Loop {
         body: Vec<Stmt>,
     },
+    While {
+        keyword: TokenSpan,
+        condition: Expr,
+        policy: IfPolicy,
+        body: Vec<Stmt>,
+    },
+    For {
+        name: TokenSpan,
+        iterable: Expr,
+        end: Option<Box<Expr>>, // for i in start..end
+        step: Option<Box<Expr>>,
+        body: Vec<Stmt>,
+    },
     Break,
     Continue,
+    Return {
+        keyword: TokenSpan,
+        value: Option<Expr>,
+    },
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -116,10 +190,18 @@ pub enum IfPolicy {
     Strict,
     Merge,
     Panic,
+    Likely(f64), // takes the branch when a partial condition is more likely than this
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct Parameter {
     pub name: TokenSpan,
     pub default: Option<Expr>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Field {
+    pub name: TokenSpan,
+    pub default: Option<Expr>,
+    pub is_public: bool,
 }
\ No newline at end of file
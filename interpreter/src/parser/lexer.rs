@@ -27,19 +27,27 @@ pub enum Token {
     Merge,
     Strict,
     None,
+    Empty,
     Print,
     Input,
     Str,
     Num,
     Panic,
     Function,
+    Struct,
+    Impl,
     Kind,
     Comma,
     Dot,
+    Colon,
 
     Loop,
+    While,
+    For,
+    In,
     Break,
     Continue,
+    Return,
 
     // Operators & Symbols
     Assign,
@@ -49,6 +57,7 @@ pub enum Token {
     Star,
     Slash,
     Caret,
+    Percent,
     RangeSep,
     
     // Delimiters
@@ -211,8 +220,10 @@ impl Lexer {
             '{' => Ok(Some(Token::LBrace)),
             '}' => Ok(Some(Token::RBrace)),
             ',' => Ok(Some(Token::Comma)),
+            ':' => Ok(Some(Token::Colon)),
             '*' => Ok(Some(Token::Star)),
             '^' => Ok(Some(Token::Caret)),
+            '%' => Ok(Some(Token::Percent)),
 
             '/' => {
                 if self.match_char('/') {
@@ -338,10 +349,17 @@ impl Lexer {
             "print" => Token::Print,
             "input" => Token::Input,
             "fn" => Token::Function,
+            "struct" => Token::Struct,
+            "impl" => Token::Impl,
             "loop" => Token::Loop,
+            "while" => Token::While,
+            "for" => Token::For,
+            "in" => Token::In,
             "break" => Token::Break,
             "continue" => Token::Continue,
+            "return" => Token::Return,
             "none" => Token::None,
+            "empty" => Token::Empty,
             "kind" => Token::Kind,
             "true" => Token::True,
             "false" => Token::False,
@@ -1,3 +1,5 @@
 pub mod lexer;
 pub mod ast;
-pub mod parser;
\ No newline at end of file
+#[allow(clippy::module_inception)]
+pub mod parser;
+pub mod resolver;
\ No newline at end of file
@@ -1,5 +1,6 @@
 use crate::parser::lexer::{Token, TokenSpan};
-use crate::parser::ast::{Expr, IfPolicy, Parameter, Stmt};
+use crate::parser::ast::{Expr, Field, IfPolicy, Parameter, Stmt};
+use crate::parser::resolver::Resolver;
 use crate::core::error::{Error, ErrorKind, ErrorReporter};
 use std::cell::RefCell;
 use std::rc::Rc;
@@ -7,6 +8,7 @@ use std::rc::Rc;
 pub struct Parser {
     tokens: Vec<TokenSpan>,
     current: usize,
+    function_depth: usize,
     reporter: Rc<RefCell<ErrorReporter>>,
 }
 
@@ -15,6 +17,7 @@ impl Parser {
         Self {
             tokens,
             current: 0,
+            function_depth: 0,
             reporter,
         }
     }
@@ -27,6 +30,8 @@ impl Parser {
             }
             statements.push(self.declaration()?);
         }
+
+        Resolver::new(self.reporter.clone()).resolve(&statements)?;
         Ok(statements)
     }
 
@@ -51,15 +56,33 @@ impl Parser {
             }
             Token::Let => {
                 self.advance();
-                self.let_declaration()
+                self.let_declaration(false)
+            }
+            Token::Const => {
+                self.advance();
+                if self.match_token(Token::Symbolic) {
+                    self.symbolic_declaration(false, true)
+                } else if self.match_token(Token::Quiet) {
+                    self.symbolic_declaration(true, true)
+                } else {
+                    self.let_declaration(true)
+                }
+            }
+            Token::Struct => {
+                self.advance();
+                self.struct_declaration()
+            }
+            Token::Impl => {
+                self.advance();
+                self.impl_declaration()
             }
             Token::Symbolic => {
                 self.advance();
-                self.symbolic_declaration(false)
+                self.symbolic_declaration(false, false)
             }
             Token::Quiet => {
                 self.advance();
-                self.symbolic_declaration(true)
+                self.symbolic_declaration(true, false)
             }
             Token::Unknown => {
                 self.advance();
@@ -77,6 +100,14 @@ impl Parser {
                 self.advance();
                 self.loop_statement()
             }
+            Token::While => {
+                self.advance();
+                self.while_statement()
+            }
+            Token::For => {
+                self.advance();
+                self.for_statement()
+            }
             Token::Break => {
                 self.advance();
                 self.end_stmt()?;
@@ -87,6 +118,10 @@ impl Parser {
                 self.end_stmt()?;
                 Ok(Stmt::Continue)
             }
+            Token::Return => {
+                self.advance();
+                self.return_statement()
+            }
             _ => self.statement(),
         }
     }
@@ -121,11 +156,76 @@ impl Parser {
         Ok(Stmt::Loop { body })
     }
 
+    fn while_statement(&mut self) -> Result<Stmt, Error> {
+        let keyword = self.previous().clone();
+        let condition = self.expression()?;
+        let policy = self.if_policy()?;
+
+        self.skip_newlines();
+        self.consume(Token::LBrace, "Expect '{' before while body")?;
+
+        let body = self.block()?;
+        Ok(Stmt::While { keyword, condition, policy, body })
+    }
+
+    fn for_statement(&mut self) -> Result<Stmt, Error> {
+        let name = self.consume_identifier("Expect loop variable after 'for'")?;
+        self.consume(Token::In, "Expect 'in' after loop variable")?;
+
+        let iterable = self.expression()?;
+        let end = if self.match_token(Token::RangeSep) {
+            Some(Box::new(self.expression()?))
+        } else {
+            None
+        };
+
+        // 'step' is not reserved, it only means something here
+        let step = if matches!(&self.peek().token, Token::Identifier(s) if s == "step") {
+            self.advance();
+            Some(Box::new(self.expression()?))
+        } else {
+            None
+        };
+
+        self.skip_newlines();
+        self.consume(Token::LBrace, "Expect '{' before for body")?;
+
+        let body = self.block()?;
+        Ok(Stmt::For { name, iterable, end, step, body })
+    }
+
     fn function_declaration(&mut self, is_public: bool) -> Result<Stmt, Error> {
         let name = self.consume_identifier("Expect function name")?;
 
         self.consume(Token::LParen, "Expect '(' after function name")?;
+        let parameters = self.parameters()?;
+        
+        self.skip_newlines();
+        self.consume(Token::LBrace, "Expect '{' before function body")?;
         
+        let body = self.function_body()?; 
+
+        Ok(Stmt::Function {
+            name,
+            params: parameters,
+            body,
+            is_public,
+        })
+    }
+
+    // Anonymous functions: fn(x) { x * 2 }
+    fn lambda(&mut self) -> Result<Expr, Error> {
+        self.consume(Token::LParen, "Expect '(' after 'fn'")?;
+        let params = self.parameters()?;
+
+        self.skip_newlines();
+        self.consume(Token::LBrace, "Expect '{' before function body")?;
+
+        let body = self.function_body()?;
+        Ok(Expr::Lambda { params, body })
+    }
+
+    fn parameters(&mut self) -> Result<Vec<Parameter>, Error> {
         let mut parameters = Vec::new();
         if !self.check(&Token::RParen) {
             loop {
@@ -142,30 +242,73 @@ impl Parser {
         }
         
         self.consume(Token::RParen, "Expect ')' after parameters")?;
-        
+        Ok(parameters)
+    }
+
+    fn struct_declaration(&mut self) -> Result<Stmt, Error> {
+        let name = self.consume_identifier("Expect struct name")?;
         self.skip_newlines();
-        self.consume(Token::LBrace, "Expect '{' before function body")?;
-        
-        let body = self.block()?; 
+        self.consume(Token::LBrace, "Expect '{' before struct fields")?;
 
-        Ok(Stmt::Function {
-            name,
-            params: parameters,
-            body,
-            is_public,
-        })
+        let mut fields = Vec::new();
+        self.skip_newlines();
+        while !self.check(&Token::RBrace) && !self.is_at_end() {
+            let is_public = self.match_token(Token::Public);
+            let field_name = self.consume_identifier("Expect field name")?;
+
+            let default = if self.match_token(Token::Assign) {
+                Some(self.expression()?)
+            } else {
+                None
+            };
+
+            fields.push(Field { name: field_name, default, is_public });
+
+            self.match_token(Token::Comma);
+            self.skip_newlines();
+        }
+
+        self.consume(Token::RBrace, "Expect '}' after struct fields")?;
+        Ok(Stmt::Struct { name, fields })
+    }
+
+    fn impl_declaration(&mut self) -> Result<Stmt, Error> {
+        let name = self.consume_identifier("Expect struct name after 'impl'")?;
+        self.skip_newlines();
+        self.consume(Token::LBrace, "Expect '{' before impl body")?;
+
+        let mut methods = Vec::new();
+        self.skip_newlines();
+        while !self.check(&Token::RBrace) && !self.is_at_end() {
+            let is_public = self.match_token(Token::Public);
+            self.consume(Token::Function, "Only functions are allowed inside 'impl'")?;
+            methods.push(self.function_declaration(is_public)?);
+            self.skip_newlines();
+        }
+
+        self.consume(Token::RBrace, "Expect '}' after impl body")?;
+        Ok(Stmt::Impl { name, methods })
     }
 
-    fn symbolic_declaration(&mut self, is_quiet: bool) -> Result<Stmt, Error> {
+    fn symbolic_declaration(&mut self, is_quiet: bool, is_const: bool) -> Result<Stmt, Error> {
         let name = self.consume_identifier("Expect variable name")?;
         self.consume(Token::Assign, "Expect '=' after name")?;
         let initializer = self.expression()?;
         self.end_stmt()?;
-        Ok(Stmt::Symbolic { name, initializer, is_quiet })
+        Ok(Stmt::Symbolic { name, initializer, is_quiet, is_const })
     }
 
     fn unknown_declaration(&mut self) -> Result<Stmt, Error> {
         let name = self.consume_identifier("Expect variable name after 'unknown'")?;
+
+        // unknown x > 0: the name is the start of its own constraint
+        if !self.is_at_end() && !self.check(&Token::NewLine) && !self.check(&Token::RBrace) {
+            self.current -= 1;
+            let constraint = self.expression()?;
+            self.end_stmt()?;
+            return Ok(Stmt::Unknown { name, constraint });
+        }
+
         self.end_stmt()?;
         
         let unknown_span = TokenSpan { 
@@ -176,18 +319,19 @@ impl Parser {
 
         Ok(Stmt::Let { 
             name, 
-            initializer: Expr::Literal { value: unknown_span } 
+            initializer: Expr::Literal { value: unknown_span },
+            is_const: false,
         })
     }
 
-    fn let_declaration(&mut self) -> Result<Stmt, Error> {
+    fn let_declaration(&mut self, is_const: bool) -> Result<Stmt, Error> {
         let name = self.consume_identifier("Expect variable name")?;
         self.consume(Token::Assign, "Expect '=' after variable name")?;
         
         let initializer = self.expression()?; 
         
         self.end_stmt()?;
-        Ok(Stmt::Let { name, initializer })
+        Ok(Stmt::Let { name, initializer, is_const })
     }
 
     
@@ -209,7 +353,8 @@ impl Parser {
     // --- Statements ---
     
     fn statement(&mut self) -> Result<Stmt, Error> {
-        if self.match_token(Token::Function) {
+        if self.check(&Token::Function) && !self.peek_next_type(Token::LParen) {
+            self.advance();
             return self.function_declaration(false)
         }
 
@@ -225,28 +370,26 @@ impl Parser {
             return Ok(Stmt::Assign { name, value });
         }
         let expr = self.expression()?;
+
+        if self.match_token(Token::Assign) {
+            let equals = self.previous().clone();
+            let value = self.expression()?;
+            self.end_stmt()?;
+            return match expr {
+                Expr::Get { object, name } => Ok(Stmt::Expression {
+                    expression: Expr::Set { object, name, value: Box::new(value) },
+                }),
+                _ => Err(self.report_error(equals, "Invalid assignment target")),
+            };
+        }
+
         self.end_stmt()?;
         Ok(Stmt::Expression { expression: expr })
     }
         
     fn if_statement(&mut self) -> Result<Stmt, Error> {
         let condition = self.expression()?;
-        
-        let policy = if self.match_token(Token::Arrow) {
-            match self.advance().token {
-                Token::Strict => IfPolicy::Strict,
-                Token::Merge => IfPolicy::Merge,
-                Token::Panic => IfPolicy::Panic,
-                _ => {
-                    return Err(self.report_error(
-                        self.previous().clone(),
-                        "Expected policy (strict, merge, panic) after '->'",
-                    ))
-                }
-            }
-        } else {
-            IfPolicy::Strict // Default to strict policy
-        };
+        let policy = self.if_policy()?;
 
         self.skip_newlines();
         let then_branch = Box::new(self.statement()?);
@@ -279,6 +422,60 @@ impl Parser {
         })
     }
 
+    fn return_statement(&mut self) -> Result<Stmt, Error> {
+        let keyword = self.previous().clone();
+        if self.function_depth == 0 {
+            return Err(self.report_error(keyword, "Cannot return from top-level code"));
+        }
+
+        let value = if self.is_at_end() || self.check(&Token::NewLine) || self.check(&Token::RBrace) {
+            None
+        } else {
+            Some(self.expression()?)
+        };
+
+        self.end_stmt()?;
+        Ok(Stmt::Return { keyword, value })
+    }
+
+    fn function_body(&mut self) -> Result<Vec<Stmt>, Error> {
+        self.function_depth += 1;
+        let body = self.block();
+        self.function_depth -= 1;
+        body
+    }
+
+    fn if_policy(&mut self) -> Result<IfPolicy, Error> {
+        if !self.match_token(Token::Arrow) {
+            return Ok(IfPolicy::Strict); // Default to strict policy
+        }
+
+        match self.advance().token.clone() {
+            Token::Strict => Ok(IfPolicy::Strict),
+            Token::Merge => Ok(IfPolicy::Merge),
+            Token::Panic => Ok(IfPolicy::Panic),
+            // likely isn't a keyword, so it stays free to use as a name
+            Token::Identifier(name) if name == "likely" => {
+                self.consume(Token::LParen, "Expected '(' after 'likely'")?;
+                let threshold = match self.advance().token {
+                    Token::Number(n) if (0.0..=1.0).contains(&n) => n,
+                    _ => {
+                        return Err(self.report_error(
+                            self.previous().clone(),
+                            "Expected a probability between 0 and 1, e.g. 'likely(0.9)'",
+                        ));
+                    }
+                };
+                self.consume(Token::RParen, "Expected ')' after the probability")?;
+                Ok(IfPolicy::Likely(threshold))
+            }
+            _ => Err(self.report_error(
+                self.previous().clone(),
+                "Expected policy (strict, merge, panic, likely(p)) after '->'",
+            )),
+        }
+    }
+
     fn panic_statement(&mut self) -> Result<Stmt, Error> {
         self.end_stmt()?;
         Ok(Stmt::Panic)
@@ -325,7 +522,7 @@ impl Parser {
     }
 
     fn multiplication(&mut self) -> Result<Expr, Error> {
-        self.binary(Self::power, &[Token::Star, Token::Slash])
+        self.binary(Self::power, &[Token::Star, Token::Slash, Token::Percent])
     }
 
     fn power(&mut self) -> Result<Expr, Error> {
@@ -347,6 +544,10 @@ impl Parser {
         loop {
             if self.match_token(Token::LParen) {
                 expr = self.finish_call(expr)?;
+            } else if self.match_token(Token::LBracket) {
+                let index = self.expression()?;
+                let bracket = self.consume(Token::RBracket, "Expect ']' after index")?.clone();
+                expr = Expr::Index { object: Box::new(expr), index: Box::new(index), bracket };
             } else if self.match_token(Token::Dot) {
                 let name = self.consume_identifier("Expect property name after '.'")?;
                 expr = Expr::Get { object: Box::new(expr), name };
@@ -386,11 +587,27 @@ impl Parser {
     fn primary(&mut self) -> Result<Expr, Error> {
         if self.match_token(Token::True) { return Ok(Expr::Literal { value: self.previous().clone() }); }
         if self.match_token(Token::False) { return Ok(Expr::Literal { value: self.previous().clone() }); }
+        // partial(f) is the builtin, partial on its own the bool
+        if self.check(&Token::Partial) && self.peek_next_type(Token::LParen) {
+            let keyword = self.advance().clone();
+            return Ok(Expr::Variable { name: TokenSpan { token: Token::Identifier("partial".to_string()), ..keyword } });
+        }
         if self.match_token(Token::Partial) { return Ok(Expr::Literal { value: self.previous().clone() }); }
         if self.match_token(Token::None) { return Ok(Expr::Literal { value: self.previous().clone() }); }
+        if self.match_token(Token::Empty) { return Ok(Expr::Literal { value: self.previous().clone() }); }
         if self.match_token(Token::Unknown) { return Ok(Expr::Literal { value: self.previous().clone() }); }
 
+        if self.match_token(Token::Function) {
+            return self.lambda();
+        }
+
         if self.match_token(Token::LBrace) {
+            if self.is_map_literal() {
+                return self.map_literal();
+            }
+            if self.is_one_of_literal() {
+                return self.one_of_literal();
+            }
             let statements = self.block()?;
             return Ok(Expr::Block { statements });
         }
@@ -419,16 +636,108 @@ impl Parser {
         }
 
         if self.match_token(Token::LBracket) {
-            let min = self.expression()?;
-            self.consume(Token::RangeSep, "Expect '..' in interval")?;
-            let max = self.expression()?;
-            let bracket = self.consume(Token::RBracket, "Expect ']' after interval")?.clone();
-            return Ok(Expr::Interval { min: Box::new(min), max: Box::new(max), bracket });
+            self.skip_newlines();
+            if self.check(&Token::RBracket) {
+                let bracket = self.advance().clone();
+                return Ok(Expr::List { elements: Vec::new(), bracket });
+            }
+
+            let first = self.expression()?;
+
+            // [a..b] is an interval, anything else is a list
+            if self.match_token(Token::RangeSep) {
+                let max = self.expression()?;
+                let bracket = self.consume(Token::RBracket, "Expect ']' after interval")?.clone();
+                return Ok(Expr::Interval { min: Box::new(first), max: Box::new(max), bracket });
+            }
+
+            let mut elements = vec![first];
+            self.skip_newlines();
+            while self.match_token(Token::Comma) {
+                self.skip_newlines();
+                if self.check(&Token::RBracket) { break; } // trailing comma
+                elements.push(self.expression()?);
+                self.skip_newlines();
+            }
+            let bracket = self.consume(Token::RBracket, "Expect ']' after list elements")?.clone();
+            return Ok(Expr::List { elements, bracket });
         }
 
         Err(self.report_error(self.peek().clone(), "Expect expression"))
     }
 
+    // '{}' or '{ key: ...' right after the brace, everything else is a block
+    fn is_map_literal(&self) -> bool {
+        let mut i = self.current;
+        while matches!(self.tokens[i].token, Token::NewLine) {
+            i += 1;
+        }
+        match self.tokens[i].token {
+            Token::RBrace => true,
+            Token::Identifier(_) | Token::String(_) => {
+                matches!(self.tokens.get(i + 1).map(|t| &t.token), Some(Token::Colon))
+            }
+            _ => false,
+        }
+    }
+
+    // {1, 3, 7} has a comma outside of any brackets, a block never does
+    fn is_one_of_literal(&self) -> bool {
+        let mut depth = 0;
+        for span in &self.tokens[self.current..] {
+            match span.token {
+                Token::LParen | Token::LBracket | Token::LBrace => depth += 1,
+                Token::RParen | Token::RBracket => depth -= 1,
+                Token::RBrace if depth == 0 => return false,
+                Token::RBrace => depth -= 1,
+                Token::Comma if depth == 0 => return true,
+                Token::EOF => return false,
+                _ => {}
+            }
+        }
+        false
+    }
+
+    fn one_of_literal(&mut self) -> Result<Expr, Error> {
+        let brace = self.previous().clone();
+        let mut options = Vec::new();
+
+        self.skip_newlines();
+        while !self.check(&Token::RBrace) {
+            options.push(self.expression()?);
+
+            self.skip_newlines();
+            if !self.match_token(Token::Comma) { break; }
+            self.skip_newlines();
+        }
+
+        self.consume(Token::RBrace, "Expect '}' after options")?;
+        Ok(Expr::OneOf { options, brace })
+    }
+
+    fn map_literal(&mut self) -> Result<Expr, Error> {
+        let brace = self.previous().clone();
+        let mut entries = Vec::new();
+
+        self.skip_newlines();
+        while !self.check(&Token::RBrace) {
+            let key = match self.peek().token {
+                Token::Identifier(_) | Token::String(_) => self.advance().clone(),
+                _ => return Err(self.report_error(self.peek().clone(), "Expect field name")),
+            };
+            self.consume(Token::Colon, "Expect ':' after field name")?;
+            let value = self.expression()?;
+            entries.push((key, value));
+
+            self.skip_newlines();
+            if !self.match_token(Token::Comma) { break; }
+            self.skip_newlines();
+        }
+
+        self.consume(Token::RBrace, "Expect '}' after map fields")?;
+        Ok(Expr::Map { entries, brace })
+    }
+
     fn end_stmt(&mut self) -> Result<(), Error> {
         if self.is_at_end() { return Ok(()); }
         if self.match_token(Token::NewLine) { return Ok(()); }
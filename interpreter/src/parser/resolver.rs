@@ -0,0 +1,177 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use crate::core::error::{Error, ErrorKind, ErrorReporter};
+use crate::parser::ast::{Expr, Parameter, Stmt};
+use crate::parser::lexer::TokenSpan;
+
+// Static checks that run once the whole file is parsed.
+// For now it catches writes to constants before the program even starts,
+// names it can't see (imports, earlier REPL lines) are left to the runtime check.
+pub struct Resolver {
+    scopes: Vec<HashMap<String, bool>>, // name -> is_const
+    reporter: Rc<RefCell<ErrorReporter>>,
+}
+
+impl Resolver {
+    pub fn new(reporter: Rc<RefCell<ErrorReporter>>) -> Self {
+        Self {
+            scopes: vec![HashMap::new()],
+            reporter,
+        }
+    }
+
+    pub fn resolve(&mut self, statements: &[Stmt]) -> Result<(), Error> {
+        for stmt in statements {
+            self.stmt(stmt)?;
+        }
+        Ok(())
+    }
+
+    fn scoped(&mut self, params: &[Parameter], statements: &[Stmt]) -> Result<(), Error> {
+        self.scopes.push(HashMap::new());
+        for param in params {
+            self.declare(&param.name, false)?;
+        }
+        let result = self.resolve(statements);
+        self.scopes.pop();
+        result
+    }
+
+    fn declare(&mut self, name: &TokenSpan, is_const: bool) -> Result<(), Error> {
+        let key = name.token_to_string();
+        let scope = self.scopes.last_mut().expect("there is always a global scope");
+        if scope.get(&key) == Some(&true) {
+            return Err(self.report_error(name, format!("Cannot redeclare constant '{}'", key)));
+        }
+        scope.insert(key, is_const);
+        Ok(())
+    }
+
+    fn check_write(&self, name: &TokenSpan) -> Result<(), Error> {
+        let key = name.token_to_string();
+        for scope in self.scopes.iter().rev() {
+            if let Some(is_const) = scope.get(&key) {
+                if *is_const {
+                    return Err(self.report_error(name, format!("Cannot assign to constant '{}'", key)));
+                }
+                return Ok(());
+            }
+        }
+        Ok(())
+    }
+
+    fn stmt(&mut self, stmt: &Stmt) -> Result<(), Error> {
+        match stmt {
+            Stmt::Let { name, initializer, is_const } => {
+                self.expr(initializer)?;
+                self.declare(name, *is_const)
+            }
+            Stmt::Symbolic { name, initializer, is_const, .. } => {
+                self.expr(initializer)?;
+                self.declare(name, *is_const)
+            }
+            Stmt::Unknown { name, .. } => self.declare(name, false),
+            Stmt::Assign { name, value } => {
+                self.expr(value)?;
+                self.check_write(name)
+            }
+            Stmt::Import { alias, path } => self.declare(alias.as_ref().unwrap_or(path), false),
+            Stmt::Struct { name, fields } => {
+                for field in fields {
+                    if let Some(default) = &field.default {
+                        self.expr(default)?;
+                    }
+                }
+                self.declare(name, false)
+            }
+            Stmt::Impl { methods, .. } => self.resolve(methods),
+            Stmt::Function { name, params, body, .. } => {
+                self.declare(name, false)?;
+                self.scoped(params, body)
+            }
+            Stmt::Print { expression } | Stmt::Expression { expression } => self.expr(expression),
+            Stmt::Return { value, .. } => match value {
+                Some(expr) => self.expr(expr),
+                None => Ok(()),
+            },
+            Stmt::Block { statements } | Stmt::Loop { body: statements } => self.scoped(&[], statements),
+            Stmt::While { condition, body, .. } => {
+                self.expr(condition)?;
+                self.scoped(&[], body)
+            }
+            Stmt::For { name, iterable, end, step, body } => {
+                self.expr(iterable)?;
+                if let Some(end) = end {
+                    self.expr(end)?;
+                }
+                if let Some(step) = step {
+                    self.expr(step)?;
+                }
+                let loop_var = Parameter { name: name.clone(), default: None };
+                self.scoped(&[loop_var], body)
+            }
+            Stmt::If { condition, then_branch, elif_branch, else_branch, .. } => {
+                self.expr(condition)?;
+                self.stmt(then_branch)?;
+                for (cond, body) in elif_branch {
+                    self.expr(cond)?;
+                    self.stmt(body)?;
+                }
+                match else_branch {
+                    Some(body) => self.stmt(body),
+                    None => Ok(()),
+                }
+            }
+            Stmt::Panic | Stmt::Break | Stmt::Continue => Ok(()),
+        }
+    }
+
+    fn expr(&mut self, expr: &Expr) -> Result<(), Error> {
+        match expr {
+            Expr::Postfix { name, .. } => self.check_write(name),
+            Expr::Block { statements } => self.scoped(&[], statements),
+            Expr::Lambda { params, body } => self.scoped(params, body),
+            Expr::Binary { left, right, .. } => {
+                self.expr(left)?;
+                self.expr(right)
+            }
+            Expr::Grouping { expression } => self.expr(expression),
+            Expr::Unary { right, .. } => self.expr(right),
+            Expr::Interval { min, max, .. } => {
+                self.expr(min)?;
+                self.expr(max)
+            }
+            Expr::Call { callee, arguments, .. } => {
+                self.expr(callee)?;
+                arguments.iter().try_for_each(|arg| self.expr(arg))
+            }
+            Expr::List { elements, .. } | Expr::OneOf { options: elements, .. } => {
+                elements.iter().try_for_each(|e| self.expr(e))
+            }
+            Expr::Map { entries, .. } => entries.iter().try_for_each(|(_, e)| self.expr(e)),
+            Expr::Index { object, index, .. } => {
+                self.expr(object)?;
+                self.expr(index)
+            }
+            Expr::Get { object, .. } => self.expr(object),
+            Expr::Set { object, value, .. } => {
+                self.expr(object)?;
+                self.expr(value)
+            }
+            Expr::Conditional { condition, then_branch, else_branch } => {
+                self.expr(condition)?;
+                self.expr(then_branch)?;
+                self.expr(else_branch)
+            }
+            Expr::Literal { .. } | Expr::Variable { .. } => Ok(()),
+        }
+    }
+
+    fn report_error(&self, token: &TokenSpan, msg: impl Into<String>) -> Error {
+        self.reporter
+            .borrow_mut()
+            .error_with_kind(ErrorKind::Syntax, token.clone(), msg)
+    }
+}
@@ -1,5 +1,5 @@
 use sk_lang::SKInterpreter;
-use sk_lang::core::value::Value;
+use sk_lang::core::value::{SKBool, Value};
 
 #[test]
 fn evals_basic_expression() {
@@ -134,4 +134,556 @@ fn evals_symbolics() {
     assert_eq!(result1, Value::Number(2.0));
     assert_eq!(result2, Value::Number(12.0));
     assert!(matches!(result3, Value::Symbolic { is_quiet: false, .. }));
-}
\ No newline at end of file
+}
+
+#[test]
+fn evals_lists() {
+    let mut interpreter = SKInterpreter::new();
+    let result1 = interpreter
+        .execute_string("let xs = [1, [0..1], 3]\nxs * 2".to_string())
+        .expect("execution should succeed");
+
+    let result2 = interpreter
+        .execute_string("push(xs, 4)\nlen(xs) + xs[3]".to_string())
+        .expect("execution should succeed");
+
+    let result3 = interpreter
+        .execute_string("fn big(x) { x > 1 }\nfilter(xs, big)".to_string())
+        .expect("execution should succeed");
+
+    assert_eq!(result1, Value::list(vec![Value::Number(2.0), Value::Interval(0.0, 2.0), Value::Number(6.0)]));
+    assert_eq!(result2, Value::Number(8.0));
+    assert_eq!(result3, Value::list(vec![Value::Number(3.0), Value::Number(4.0)]));
+
+    let aliased = interpreter
+        .execute_string("let ys = [1]
+let zs = ys
+push(zs, 2)
+push(ys, ys)
+[ys, zs == ys]".to_string())
+        .expect("execution should succeed");
+    assert_eq!(aliased.to_string(), "[[1, 2, [1, 2]], true]");
+}
+
+#[test]
+fn evals_maps() {
+    let mut interpreter = SKInterpreter::new();
+    let result1 = interpreter
+        .execute_string("let m = { temp: [18..24], unit: 'C' }\nm.temp = m.temp + 1\nm.temp".to_string())
+        .expect("execution should succeed");
+
+    let result2 = interpreter
+        .execute_string("m.count = 2\nm['count'] * len(m)".to_string())
+        .expect("execution should succeed");
+
+    assert_eq!(result1, Value::Interval(19.0, 25.0));
+    assert_eq!(result2, Value::Number(6.0));
+}
+
+#[test]
+fn evals_structs() {
+    let mut interpreter = SKInterpreter::new();
+    let source = "struct M {\npub value\nunit = 'C'\n}\nimpl M {\npub fn widen(self, by) { self.value = self.value + by }\n}\nlet m = M([1..2])\nm.widen(1)\nm.value";
+    let result = interpreter
+        .execute_string(source.to_string())
+        .expect("execution should succeed");
+
+    assert_eq!(result, Value::Interval(2.0, 3.0));
+    assert!(interpreter.execute_string("m.unit".to_string()).is_err());
+    assert!(interpreter.execute_string("fn peek(self) { self.unit }\npeek(m)".to_string()).is_err());
+    assert!(interpreter.execute_string("let self = m\nself.unit".to_string()).is_err());
+}
+
+#[test]
+fn evals_closures() {
+    let mut interpreter = SKInterpreter::new();
+    let result1 = interpreter
+        .execute_string("fn adder(n) { fn(x) { x + n } }\nlet add2 = adder(2)\nadd2(3)".to_string())
+        .expect("execution should succeed");
+
+    let result2 = interpreter
+        .execute_string("fn counter() {\nlet c = 0\nfn() {\nc = c + 1\nc\n}\n}\nlet next = counter()\nnext()\nnext()".to_string())
+        .expect("execution should succeed");
+
+    assert_eq!(result1, Value::Number(5.0));
+    assert_eq!(result2, Value::Number(2.0));
+}
+
+#[test]
+fn evals_returns() {
+    let mut interpreter = SKInterpreter::new();
+    let result1 = interpreter
+        .execute_string("fn f(xs) {\nlet i = 0\nloop {\nif xs[i] > 2 { return xs[i] }\ni++\n}\n}\nf([1, 5, 7])".to_string())
+        .expect("execution should succeed");
+
+    let result2 = interpreter
+        .execute_string("fn sign(x) {\nif x > 0 -> merge { return 1 } else { return -1 }\n}\nsign([-1..1])".to_string())
+        .expect("execution should succeed");
+
+    let result3 = interpreter
+        .execute_string("fn f(x) {\nif x > 0 -> merge { return 1 }\n2\n}\n[f([-1..1]), f(3), f(-3)]".to_string())
+        .expect("execution should succeed");
+
+    assert_eq!(result1, Value::Number(5.0));
+    assert_eq!(result2, Value::Interval(-1.0, 1.0));
+    assert_eq!(result3, Value::list(vec![Value::Interval(1.0, 2.0), Value::Number(1.0), Value::Number(2.0)]));
+    assert!(interpreter.execute_string("fn g(x) {\nif x > 0 -> merge { return 1 }\nnope\n}\ng([-1..1])".to_string()).is_err());
+}
+
+#[test]
+fn evals_while_and_for() {
+    let mut interpreter = SKInterpreter::new();
+    let result1 = interpreter
+        .execute_string("let total = 0\nfor i in 0..5 { total = total + i }\nfor x in [0..1] step 0.5 { total = total + x }\ntotal".to_string())
+        .expect("execution should succeed");
+
+    let result2 = interpreter
+        .execute_string("let x = [0..20]\nwhile x < 10 -> merge { x = x + 1 }\nx".to_string())
+        .expect("execution should succeed");
+
+    assert_eq!(result1, Value::Number(11.5));
+    assert_eq!(result2, Value::Interval(10.0, 20.0));
+    assert!(interpreter.execute_string("let y = [0..20]\nwhile y > 5 -> merge { y = y + 1 }".to_string()).is_err());
+
+    let result3 = interpreter
+        .execute_string("let n = 0
+for i in 0..1e9 {
+n = n + 1
+if i >= 2 { break }
+}
+n".to_string())
+        .expect("execution should succeed");
+    assert_eq!(result3, Value::Number(3.0));
+    assert!(interpreter.execute_string("unknown p > 0\nfor v in [0..1] + 3 / p step 1 { }".to_string()).is_err());
+}
+
+#[test]
+fn evals_constants() {
+    let mut interpreter = SKInterpreter::new();
+    let result = interpreter
+        .execute_string("const G = 9.81\n{ let G = 1\nG = 2 }\nG".to_string())
+        .expect("execution should succeed");
+
+    assert_eq!(result, Value::Number(9.81));
+    assert!(interpreter.execute_string("G = 10".to_string()).is_err());
+    assert!(interpreter.execute_string("fn f() { G = 3 }\nf()".to_string()).is_err());
+    assert!(interpreter.execute_string("const H = 1\nH++".to_string()).is_err());
+
+    // narrowing a constant inside a merged branch must not replace it, an affine constant still cancels out
+    let mut interpreter = SKInterpreter::new();
+    interpreter.set_affine_arithmetic(true);
+    let narrowed = interpreter
+        .execute_string("const c = [0..10]\nlet a = 0\nif c > 5 -> merge { a = 1 }\n(c + 1) - c".to_string())
+        .expect("execution should succeed");
+    let looped = interpreter
+        .execute_string("let n = 0\nwhile c > 5 -> merge {\nn = n + 1\nbreak\n}\n(c + 1) - c".to_string())
+        .expect("execution should succeed");
+    assert_eq!(narrowed, Value::Number(1.0));
+    assert_eq!(looped, Value::Number(1.0));
+}
+
+#[test]
+fn evals_constrained_unknowns() {
+    let mut interpreter = SKInterpreter::new();
+    let result1 = interpreter
+        .execute_string("unknown x > 0\nx > -1".to_string())
+        .expect("execution should succeed");
+
+    let result2 = interpreter
+        .execute_string("unknown n % 2 == 0\n[n % 2, n == 3, n == 4]".to_string())
+        .expect("execution should succeed");
+
+    assert_eq!(result1, Value::Bool(SKBool::True));
+    assert_eq!(result2.to_string(), "[0, false, partial]");
+
+    let result3 = interpreter
+        .execute_string("[x / x, 3 / x, 7 % [2..3], 1 % [2..3]]".to_string())
+        .expect("execution should succeed");
+    assert_eq!(
+        result3,
+        Value::list(vec![
+            Value::Number(1.0),
+            Value::Interval(0.0, f64::INFINITY),
+            Value::Interval(0.0, 3.0),
+            Value::Number(1.0),
+        ])
+    );
+    assert!(interpreter.execute_string("unknown y > 3 && y < 2".to_string()).is_err());
+}
+
+#[test]
+fn evals_explain() {
+    let mut interpreter = SKInterpreter::new();
+    let result = interpreter
+        .execute_string("let x = [0..10]\nlet y = x * 2\nexplain(y)\nexplain(x > 5)\nsymbolic s = x + k\nexplain(s)\ny".to_string())
+        .expect("execution should succeed");
+
+    assert_eq!(result, Value::Interval(0.0, 20.0));
+    assert_eq!(
+        interpreter.explain("y").as_deref(),
+        Some("[0..20] is uncertain because:\n  line 1: written as the interval [0..10]\n  line 2: [0..10] * 2 widened the range to [0..20]")
+    );
+    assert_eq!(interpreter.explain("s").as_deref(), Some("k + x still depends on: k, x"));
+    assert!(interpreter.execute_string("explain()".to_string()).is_err());
+}
+
+#[test]
+fn evals_narrowing() {
+    let mut interpreter = SKInterpreter::new();
+    let result = interpreter
+        .execute_string("let x = [0..10]\nlet a = 0\nlet b = 0\nif x > 5 -> merge { a = x } else { b = x }\n[a, b, x]".to_string())
+        .expect("execution should succeed");
+
+    assert_eq!(
+        result,
+        Value::list(vec![Value::Interval(5.0, 10.0), Value::Interval(0.0, 5.0), Value::Interval(0.0, 10.0)])
+    );
+}
+
+#[test]
+fn evals_narrowed_assignments() {
+    let mut interpreter = SKInterpreter::new();
+    let result = interpreter
+        .execute_string(
+            "let x = [0..10]\nif x > 5 -> merge { x = x + 1 }\nlet y = [0..10]\nif y > 5 -> merge { y = y + 1 } else { y = y - 1 }\n[x, y]"
+                .to_string(),
+        )
+        .expect("execution should succeed");
+
+    assert_eq!(result, Value::list(vec![Value::Interval(0.0, 11.0), Value::Interval(-1.0, 11.0)]));
+}
+
+#[test]
+fn evals_merged_values() {
+    let mut interpreter = SKInterpreter::new();
+    let result = interpreter
+        .execute_string(
+            "fn pick(v, a, b) {\nif v > 5 -> merge { return a } else { return b }\n}\n[pick([0..10], true, false), pick([0..10], \"big\", \"small\"), pick([0..10], 3, none)]"
+                .to_string(),
+        )
+        .expect("execution should succeed");
+
+    assert_eq!(
+        result,
+        Value::list(vec![
+            Value::Bool(SKBool::Partial),
+            Value::OneOf(vec![Value::String("big".to_string()), Value::String("small".to_string())]),
+            Value::OneOf(vec![Value::Number(3.0), Value::None]),
+        ])
+    );
+}
+
+#[test]
+fn evals_one_of() {
+    let mut interpreter = SKInterpreter::new();
+    let result1 = interpreter
+        .execute_string("let a = {1, 3, 7}\na + 1".to_string())
+        .expect("execution should succeed");
+
+    let result2 = interpreter
+        .execute_string("[a > 0, a == 3, {\"red\", \"green\"} == \"blue\"]".to_string())
+        .expect("execution should succeed");
+
+    let result3 = interpreter
+        .execute_string("let big = {1, 2, 3, 4, 5, 6, 7, 8, 9}\nbig * big".to_string())
+        .expect("execution should succeed");
+
+    assert_eq!(result1, Value::OneOf(vec![Value::Number(2.0), Value::Number(4.0), Value::Number(8.0)]));
+    assert_eq!(
+        result2,
+        Value::list(vec![Value::Bool(SKBool::True), Value::Bool(SKBool::Partial), Value::Bool(SKBool::False)])
+    );
+    assert_eq!(result3, Value::Interval(1.0, 81.0));
+
+    let result4 = interpreter
+        .execute_string("let b = {1, 2}
+[b - b, b / b]".to_string())
+        .expect("execution should succeed");
+    assert_eq!(result4, Value::list(vec![Value::Number(0.0), Value::Number(1.0)]));
+}
+
+#[test]
+fn evals_interval_unions() {
+    let mut interpreter = SKInterpreter::new();
+    let result1 = interpreter
+        .execute_string("import math\nlet u = math.union([0..1], [9..10])\nu + 1".to_string())
+        .expect("execution should succeed");
+
+    let result2 = interpreter
+        .execute_string("[math.intersection([0..1], [2..3]), math.complement([0..1]), math.contains(u, 5)]".to_string())
+        .expect("execution should succeed");
+
+    assert_eq!(result1, Value::Union(vec![(1.0, 2.0), (10.0, 11.0)]));
+    assert_eq!(
+        result2,
+        Value::list(vec![
+            Value::Empty,
+            Value::Union(vec![(f64::NEG_INFINITY, 0.0), (1.0, f64::INFINITY)]),
+            Value::Bool(SKBool::False),
+        ])
+    );
+
+    let result3 = interpreter
+        .execute_string("let e = math.intersection([0..1], [2..3])
+[e == e, e != e, e != 1, e < 1]".to_string())
+        .expect("execution should succeed");
+    assert_eq!(
+        result3,
+        Value::list(vec![
+            Value::Bool(SKBool::False),
+            Value::Bool(SKBool::True),
+            Value::Bool(SKBool::True),
+            Value::Bool(SKBool::False),
+        ])
+    );
+}
+
+#[test]
+fn evals_extended_division() {
+    let mut interpreter = SKInterpreter::new();
+    let result = interpreter
+        .execute_string("1 / [-1..2]".to_string())
+        .expect("execution should succeed");
+
+    assert_eq!(result, Value::Union(vec![(f64::NEG_INFINITY, -1.0), (0.5, f64::INFINITY)]));
+    assert_eq!(result.to_string(), "[-inf..-1] ∪ [0.5..inf]");
+
+    interpreter.set_strict_division(true);
+    assert!(interpreter.execute_string("1 / [-1..2]".to_string()).is_err());
+}
+
+#[test]
+fn evals_outward_rounding() {
+    let mut interpreter = SKInterpreter::new();
+    let code = "[0.1..0.2] + [0.2..0.3]".to_string();
+
+    let nearest = interpreter.execute_string(code.clone()).expect("execution should succeed");
+    assert_eq!(nearest, Value::Interval(0.1 + 0.2, 0.5));
+
+    interpreter.set_outward_rounding(true);
+    let Value::Interval(min, max) = interpreter.execute_string(code).expect("execution should succeed") else {
+        panic!("expected an interval");
+    };
+    assert!(min < 0.1 + 0.2 && max > 0.5);
+    assert_eq!(interpreter.execute_string("2 * 3".to_string()).unwrap(), Value::Number(6.0));
+}
+
+#[test]
+fn evals_interval_math() {
+    let mut interpreter = SKInterpreter::new();
+    let result = interpreter
+        .execute_string("import math\n[math.abs([-3..2]), math.exp([0..1]), math.cos([-1..1]), math.tan([1..2])]".to_string())
+        .expect("execution should succeed");
+
+    assert_eq!(
+        result,
+        Value::list(vec![
+            Value::Interval(0.0, 3.0),
+            Value::Interval(1.0, std::f64::consts::E),
+            Value::Interval(1f64.cos(), 1.0),
+            Value::Union(vec![(f64::NEG_INFINITY, 2f64.tan()), (1f64.tan(), f64::INFINITY)]),
+        ])
+    );
+
+    let result = interpreter
+        .execute_string("let x = unknown\nsymbolic z = x + 2\nlet t = math.sin(z)\nx = 1\nresolve(t)".to_string())
+        .expect("execution should succeed");
+    assert_eq!(result, Value::Number(3f64.sin()));
+
+    let result = interpreter
+        .execute_string("[map([z], math.sin), math.sqrt([-1..4])]".to_string())
+        .expect("execution should succeed");
+    assert_eq!(result.to_string(), "[[math.sin(x + 2)], [0..2]]");
+}
+
+#[test]
+fn evals_interval_powers() {
+    let mut interpreter = SKInterpreter::new();
+    let result = interpreter
+        .execute_string("[[-1..2]^-2, [1..4]^0.5, 2^[1..3], [1..2]^[2..3]]".to_string())
+        .expect("execution should succeed");
+
+    assert_eq!(
+        result,
+        Value::list(vec![
+            Value::Interval(0.25, f64::INFINITY),
+            Value::Interval(1.0, 2.0),
+            Value::Interval(2.0, 8.0),
+            Value::Interval(1.0, 8.0),
+        ])
+    );
+
+    let result = interpreter
+        .execute_string("[[-1..4]^0.5, (-8)^(1/3), [-8..8]^(1/3), [0..2]^0]".to_string())
+        .expect("execution should succeed");
+    assert_eq!(
+        result,
+        Value::list(vec![
+            Value::Interval(0.0, 2.0),
+            Value::Number(-2.0),
+            Value::Interval(-2.0, 2.0),
+            Value::Number(1.0),
+        ])
+    );
+    assert!(interpreter.execute_string("[-4..-1]^0.5".to_string()).is_err());
+}
+
+#[test]
+fn evals_affine_arithmetic() {
+    let mut interpreter = SKInterpreter::new();
+    interpreter.set_affine_arithmetic(true);
+    let result = interpreter
+        .execute_string("let x = [0..1]\nlet y = [0..1]\n[x * (1 - x), x - y, kind(x)]".to_string())
+        .expect("execution should succeed");
+
+    assert_eq!(result.to_string(), "[[0..0.25], [-1..1], interval]");
+}
+
+#[test]
+fn evals_distributions() {
+    let mut interpreter = SKInterpreter::new();
+    interpreter.set_sample_count(20000);
+    let result = interpreter
+        .execute_string("import dist\nlet d = dist.uniform(0, 10)\n[dist.mean(d * 2), d - d, dist.probability(d > 7.5), d > 20]".to_string())
+        .expect("execution should succeed");
+
+    let Value::List(items) = result else { panic!("expected a list") };
+    let items = items.borrow();
+    let (Value::Number(mean), Value::Number(p)) = (&items[0], &items[2]) else { panic!("expected numbers") };
+    assert!((mean - 10.0).abs() < 0.5);
+    assert!((p - 0.25).abs() < 0.05);
+    assert_eq!(items[1], Value::Number(0.0));
+    assert_eq!(items[3], Value::Bool(SKBool::False));
+
+    // both sides come from the same samples, so they are not independent
+    let result = interpreter
+        .execute_string("[dist.probability(d > 5 && d > 5), d > 5 || d < 7, dist.probability(d > 5 && d < 2)]".to_string())
+        .expect("execution should succeed");
+    let Value::List(items) = result else { panic!("expected a list") };
+    let items = items.borrow();
+    let Value::Number(both) = items[0] else { panic!("expected a number") };
+    assert!((both - 0.5).abs() < 0.05);
+    assert_eq!(items[1], Value::Bool(SKBool::True));
+    assert_eq!(items[2], Value::Number(0.0));
+}
+
+#[test]
+fn evals_likely_policy() {
+    let mut interpreter = SKInterpreter::new();
+    let result = interpreter
+        .execute_string("import dist\nlet x = [10..20]\nlet a = 0\nlet b = 0\nif x > 12 -> likely(0.7) { a = 1 } else { a = 2 }\nif x > 18 -> likely(0.7) { b = 1 } else { b = 2 }\n[a, b, x > [15..25], dist.probability(x > [15..25])]".to_string())
+        .expect("execution should succeed");
+
+    assert_eq!(result.to_string(), "[1, 2, partial, 0.125]");
+    assert!(interpreter.execute_string("if x > 12 -> likely(2) { }".to_string()).is_err());
+}
+
+#[test]
+fn evals_symbolic_simplification() {
+    let mut interpreter = SKInterpreter::new();
+    let result = interpreter
+        .execute_string("symbolic s = a + a + 2*a\nsymbolic t = (x + 1) * 2 - 2\nsymbolic u = x * x^2 / x + 3 - x * x\nsymbolic v = (b + a) / (a + b) + 1 - y\n[s, t, u, v]".to_string())
+        .expect("execution should succeed");
+
+    assert_eq!(result.to_string(), "[4 * a, 2 * x, 3, 2 - y]");
+
+    let sums = interpreter
+        .execute_string("symbolic p = a * (b - c)\nsymbolic q = (-c + b) * a\n[p, q]".to_string())
+        .expect("execution should succeed");
+    let Value::List(items) = sums else { panic!("expected a list") };
+    let items = items.borrow();
+    assert_eq!(items[0].to_string(), "a * (b - c)");
+    assert_eq!(items[0], items[1]);
+
+    let cancelled = interpreter
+        .execute_string("symbolic d = a * (b + c) - a * b\nsymbolic e = (x + 1) * (x - 1) - x ^ 2\nsymbolic f = a * c\n[d, e, f]".to_string())
+        .expect("execution should succeed");
+    assert_eq!(cancelled.to_string(), "[a * c, -1, a * c]");
+    let Value::List(items) = cancelled else { panic!("expected a list") };
+    let items = items.borrow();
+    assert_eq!(items[0], items[2]);
+}
+
+#[test]
+fn evals_derivatives() {
+    let mut interpreter = SKInterpreter::new();
+    let result = interpreter
+        .execute_string("symbolic f = 3 * x^2 + 2 * x + 1\nsymbolic g = f * y\nlet x = 2\n[derive(f, \"x\"), derive(g, \"x\"), resolve(derive(f, \"x\")), derive(f, \"z\")]".to_string())
+        .expect("execution should succeed");
+
+    assert_eq!(result.to_string(), "[6 * x + 2, y * (6 * x + 2), 14, 0]");
+    assert!(interpreter.execute_string("symbolic h = x % 2\nderive(h, \"x\")".to_string()).is_err());
+    assert!(interpreter.execute_string("symbolic p = 2 ^ w\nderive(p, \"w\")".to_string()).is_err());
+
+    let result = interpreter
+        .execute_string("import math\nsymbolic a = math.atan2(v, w)\nsymbolic m = math.min(w, 2 * v)\n[derive(a, \"w\"), derive(a, \"v\"), derive(m, \"v\"), derive(p, \"w\")]".to_string())
+        .expect("execution should succeed");
+    assert_eq!(
+        result.to_string(),
+        "[-v / (v ^ 2 + w ^ 2), w / (v ^ 2 + w ^ 2), if w < 2 * v { 0 } else { 2 }, math.ln(2) * 2 ^ w]"
+    );
+}
+
+#[test]
+fn evals_solve() {
+    let mut interpreter = SKInterpreter::new();
+    let result = interpreter
+        .execute_string("let a = 3\nsymbolic total = a + b\nsolve(total == 10, \"b\")\nunknown x\nunknown y\nlet c = [1..2]\n[b, solve(x^2 + 2*x == 3, \"x\"), x, solve(2 * y + c == 10, \"y\")]".to_string())
+        .expect("execution should succeed");
+
+    assert_eq!(result.to_string(), "[7, {-3, 1}, {-3, 1}, [4..4.5]]");
+    assert!(interpreter.execute_string("import math\nunknown s\nsolve(math.sin(s) == 1, \"s\")".to_string()).is_err());
+
+    let result = interpreter
+        .execute_string("let u = unknown\n[kind(u == 3), (u == 3) || true, solve(u == 3, \"u\"), solve(2 * z == 4, \"z\"), u, z]".to_string())
+        .expect("execution should succeed");
+    assert_eq!(result.to_string(), "[bool, true, 3, 2, 3, 2]");
+    assert!(interpreter.execute_string("print(colour == 3)".to_string()).is_err());
+
+    // a constrained unknown only takes the roots it allows
+    let constrained = interpreter
+        .execute_string("unknown p > 0
+unknown q < -5
+[solve(p^2 == 4, \"p\"), p, solve(q^2 == 4, \"q\")]".to_string())
+        .expect("execution should succeed");
+    assert_eq!(constrained.to_string(), "[2, 2, empty]");
+}
+
+#[test]
+fn evals_substitution() {
+    let mut interpreter = SKInterpreter::new();
+    let result = interpreter
+        .execute_string("import math\nlet a = 4\nsymbolic f = x^2 + y\nsymbolic z = math.sqrt(a) * b + a\nsymbolic g = 2 * y\n[subst(f, {x: 3}), subst(f, {x: g}), partial(z), partial]".to_string())
+        .expect("execution should succeed");
+
+    assert_eq!(result.to_string(), "[y + 9, 4 * y ^ 2 + y, 2 * b + 4, partial]");
+
+    let fields = interpreter
+        .execute_string("let p = {x: 1}
+let l = [5, 6]
+symbolic q = p.x + w
+symbolic r = l[1] * {a, 4}
+[partial(q), partial(r)]".to_string())
+        .expect("execution should succeed");
+    assert_eq!(fields.to_string(), "[w + 1, 24]");
+}
+
+#[test]
+fn evals_symbolic_calls_and_unary() {
+    let mut interpreter = SKInterpreter::new();
+    let result = interpreter
+        .execute_string("import math\nsymbolic s = math.sqrt(x) - -y\nsymbolic n = -(p + q)\nsymbolic c = !flag\nsymbolic i = x + [0..1]\nlet t = -s\n[s, n, c, i, t]".to_string())
+        .expect("execution should succeed");
+    assert_eq!(result.to_string(), "[math.sqrt(x) + y, -p - q, !flag, x + [0..1], -math.sqrt(x) - y]");
+
+    let resolved = interpreter
+        .execute_string("let x = 4\nlet y = 1\nlet flag = false\n[resolve(t), resolve(c), resolve(i), -[1..2]]".to_string())
+        .expect("execution should succeed");
+    assert_eq!(resolved.to_string(), "[-3, true, [4..5], [-2..-1]]");
+
+    let negated = interpreter
+        .execute_string("unknown n > 3
+-n".to_string())
+        .expect("execution should succeed");
+    assert_eq!(negated, Value::Interval(f64::NEG_INFINITY, -3.0));
+}